@@ -0,0 +1,128 @@
+use std::io::{BufRead, BufReader, Read};
+use std::time::{Duration, Instant};
+
+use ensured_bufreader::bench::RepeatReader;
+use ensured_bufreader::EnsuredBufReader;
+
+const INPUT_SIZE: u64 = 64 * 1024 * 1024;
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    for &chunk in &[64, 4096, 65536] {
+        bench(
+            &format!("std BufReader fill/consume, chunk = {}", chunk),
+            || {
+                let r = BufReader::new(input(chunk));
+                drain(r)
+            },
+        );
+        for &ensured in &[1, 128, 4096] {
+            bench(
+                &format!(
+                    "EnsuredBufReader fill/consume, chunk = {}, ensured = {}",
+                    chunk, ensured
+                ),
+                || {
+                    let r = EnsuredBufReader::with_capacity_and_ensured_size(
+                        8 * 1024,
+                        ensured,
+                        input(chunk),
+                    );
+                    drain(r)
+                },
+            );
+        }
+    }
+
+    // Build with `--features memchr` to compare searching by `memchr`.
+    let line = b"0123456789abcdefghijklmnopqrstuvwxyz0123456789abcdefghijklmnopqrstuvwxyz0123456789abcdefghijklmnopqrstuvwxyz0123456789abcdefgh\n";
+    bench("std BufReader read_until", || {
+        let r = BufReader::new(RepeatReader::new(line, 4096).take(INPUT_SIZE));
+        read_lines(r)
+    });
+    bench("EnsuredBufReader read_until", || {
+        let r = EnsuredBufReader::new(RepeatReader::new(line, 4096).take(INPUT_SIZE));
+        read_lines(r)
+    });
+
+    for &chunk in &[4096, 65536] {
+        bench(
+            &format!("std BufReader read_to_end, chunk = {}", chunk),
+            || {
+                let r = BufReader::new(input(chunk));
+                read_to_end(r)
+            },
+        );
+        bench(
+            &format!("EnsuredBufReader default read_to_end, chunk = {}", chunk),
+            || {
+                let r = ReadOnly(EnsuredBufReader::new(input(chunk)));
+                read_to_end(r)
+            },
+        );
+        bench(
+            &format!("EnsuredBufReader read_to_end, chunk = {}", chunk),
+            || {
+                let r = EnsuredBufReader::new(input(chunk));
+                read_to_end(r)
+            },
+        );
+    }
+}
+
+fn input(chunk: usize) -> impl Read {
+    RepeatReader::new(b"0123456789abcdef", chunk).take(INPUT_SIZE)
+}
+
+fn read_lines<R: BufRead>(mut r: R) -> u64 {
+    let mut total = 0u64;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let n = r.read_until(b'\n', &mut line).unwrap();
+        if n == 0 {
+            return total;
+        }
+        total += n as u64;
+    }
+}
+
+// Hides overridden `read_to_end()` to measure the default implementation.
+struct ReadOnly<R>(R);
+
+impl<R: Read> Read for ReadOnly<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+fn read_to_end<R: Read>(mut r: R) -> u64 {
+    let mut buf = Vec::new();
+    r.read_to_end(&mut buf).unwrap() as u64
+}
+
+fn drain<R: BufRead>(mut r: R) -> u64 {
+    let mut total = 0u64;
+    loop {
+        let n = r.fill_buf().unwrap().len();
+        if n == 0 {
+            return total;
+        }
+        r.consume(n);
+        total += n as u64;
+    }
+}
+
+fn bench<F: FnMut() -> u64>(name: &str, mut f: F) {
+    let mut elapsed = Duration::new(0, 0);
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let n = f();
+        elapsed += start.elapsed();
+        assert_eq!(n, INPUT_SIZE);
+    }
+
+    let secs = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9;
+    let throughput = (INPUT_SIZE * u64::from(ITERATIONS)) as f64 / secs / (1024.0 * 1024.0);
+    println!("{}: {:.1} MiB/s", name, throughput);
+}
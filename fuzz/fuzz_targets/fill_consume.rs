@@ -0,0 +1,106 @@
+//! Drives random sequence of operations against `EnsuredBufReader` and checks it against a model.
+//!
+//! Input layout is `[capacity, ensured_size, max_chunk, data_len, ops...]`.
+//! The model is the rest of the stream, i.e. buffered bytes followed by bytes not yet read.
+#![no_main]
+
+use std::cmp;
+use std::io::{self, BufRead, Read};
+
+use ensured_bufreader::EnsuredBufReader;
+use libfuzzer_sys::fuzz_target;
+
+// Returns chunks of varying size, up to `max_chunk`.
+struct Chunked {
+    data: Vec<u8>,
+    pos: usize,
+    max_chunk: usize,
+    reads: usize,
+}
+
+impl Read for Chunked {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reads += 1;
+        let chunk = 1 + self.reads % self.max_chunk;
+        let n = cmp::min(cmp::min(chunk, buf.len()), self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fuzz_target!(|input: &[u8]| {
+    if input.len() < 4 {
+        return;
+    }
+    let ensured_size = 1 + input[1] as usize % 64;
+    let capacity = ensured_size + input[0] as usize % 64;
+    let max_chunk = 1 + input[2] as usize;
+    // Period of 251 makes misplacement by a multiple of 256 visible.
+    let data: Vec<u8> = (0..input[3] as usize * 8).map(|i| (i % 251) as u8).collect();
+    let ops = &input[4..];
+
+    let inner = Chunked {
+        data: data.clone(),
+        pos: 0,
+        max_chunk,
+        reads: 0,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(capacity, ensured_size, inner);
+    let mut model = data;
+    let mut position = 0u64;
+
+    for pair in ops.chunks(2) {
+        let arg = pair.get(1).copied().unwrap_or(0) as usize;
+        match pair[0] % 6 {
+            0 => {
+                let buf = r.fill_buf().unwrap();
+                assert!(buf.len() >= cmp::min(ensured_size, model.len()));
+                assert_eq!(buf, &model[..buf.len()]);
+            }
+            1 => {
+                let n = arg % (capacity + 4);
+                match r.fill_buf_to_expected_size(n) {
+                    Ok(buf) => {
+                        assert!(buf.len() >= cmp::min(n, model.len()));
+                        assert_eq!(buf, &model[..buf.len()]);
+                    }
+                    Err(e) => {
+                        assert!(n > capacity);
+                        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+                    }
+                }
+            }
+            2 => {
+                let amt = arg % (r.current_bytes() + 1);
+                r.consume(amt);
+                model.drain(..amt);
+                position += amt as u64;
+            }
+            3 => {
+                let n = arg % (r.current_bytes() + 1);
+                let buffered = r.current_bytes();
+                r.truncate_buffered(n);
+                model.drain(n..buffered);
+            }
+            4 => {
+                r.compact_if_tail_below(arg);
+            }
+            _ => {
+                let mut out = vec![0; arg % 32];
+                let n = r.read(&mut out).unwrap();
+                assert_eq!(&out[..n], &model[..n]);
+                model.drain(..n);
+                position += n as u64;
+            }
+        }
+
+        assert!(r.current_bytes() <= r.get_capacity());
+        assert_eq!(r.buffer(), &model[..r.current_bytes()]);
+        assert_eq!(r.position(), position);
+    }
+
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, model);
+});
@@ -0,0 +1,33 @@
+use std::io::Read;
+
+use crate::EnsuredBufReader;
+
+impl<R: Read, const N: usize> EnsuredBufReader<R, [u8; N]> {
+    /// Creates a new `EnsuredBufReader` backed by `[u8; N]` array and a default _ensured_ size (`DEFAULT_ENSURED_BYTES`).
+    ///
+    /// The array is moved into the reader, so no allocation is needed.
+    /// Note that large `N` may overflow the stack.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is smaller than `DEFAULT_ENSURED_BYTES`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::<_, [u8; 256]>::with_array(input);
+    ///
+    ///     assert_eq!(r.get_capacity(), 256);
+    ///     assert_eq!(r.fill_buf()?, b"abcdef");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_array(inner: R) -> EnsuredBufReader<R, [u8; N]> {
+        EnsuredBufReader::from_buffer([0u8; N], inner)
+    }
+}
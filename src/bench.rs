@@ -0,0 +1,68 @@
+//! Mock readers for benchmarking code built on `EnsuredBufReader`.
+//!
+//! This module is available with `bench` feature.
+
+use std::io::{self, Read};
+
+/// A reader that endlessly repeats a byte pattern, yielding at most `chunk` bytes per `read()` call.
+///
+/// Each `read()` is a single copy, so benchmarks measure the cost of the reader built on it.
+/// Use [`Read::take`](https://doc.rust-lang.org/std/io/trait.Read.html#method.take) to limit the length of the stream.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// use ensured_bufreader::bench::RepeatReader;
+///
+/// let mut r = RepeatReader::new(b"abc", 4);
+/// let mut buf = [0u8; 8];
+/// assert_eq!(r.read(&mut buf).unwrap(), 4);
+/// assert_eq!(&buf[..4], b"abca");
+/// assert_eq!(r.read(&mut buf).unwrap(), 4);
+/// assert_eq!(&buf[..4], b"bcab");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RepeatReader {
+    data: Vec<u8>,
+    pattern_len: usize,
+    offset: usize,
+    chunk: usize,
+}
+
+impl RepeatReader {
+    /// Creates a new `RepeatReader` that repeats `pattern` and yields at most `chunk` bytes per `read()` call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is empty or `chunk` is 0.
+    pub fn new(pattern: &[u8], chunk: usize) -> RepeatReader {
+        assert!(!pattern.is_empty(), "'pattern' must not be empty.");
+        assert_ne!(chunk, 0, "'chunk' must be positive.");
+
+        // Any `chunk` bytes starting from any offset in the pattern can be taken as one slice.
+        let mut data = Vec::with_capacity(chunk + 2 * pattern.len());
+        while data.len() < chunk + pattern.len() {
+            data.extend_from_slice(pattern);
+        }
+        RepeatReader {
+            data,
+            pattern_len: pattern.len(),
+            offset: 0,
+            chunk,
+        }
+    }
+}
+
+impl Read for RepeatReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = if buf.len() < self.chunk {
+            buf.len()
+        } else {
+            self.chunk
+        };
+        buf[..n].copy_from_slice(&self.data[self.offset..self.offset + n]);
+        self.offset = (self.offset + n) % self.pattern_len;
+        Ok(n)
+    }
+}
@@ -0,0 +1,299 @@
+use std::io::{self, BufRead, Read};
+use std::mem;
+
+use crate::{EnsuredBufReader, RecordTooLongError};
+
+/// Byte order used by endianness-neutral numeric methods such as [`.read_u32()`](struct.EnsuredBufReader.html#method.read_u32).
+///
+/// Default is `Endian::Big`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Endian {
+    /// Big-endian (network byte order).
+    Big,
+    /// Little-endian.
+    Little,
+}
+
+impl Default for Endian {
+    fn default() -> Endian {
+        Endian::Big
+    }
+}
+
+macro_rules! read_num {
+    ($(#[$attr:meta])* $name:ident, $t:ty, $from:ident) => {
+        $(#[$attr])*
+        ///
+        /// # Errors
+        ///
+        /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if EOF is reached before enough bytes are read.
+        /// Buffered bytes are kept in this case, unless the value is larger than _capacity_.
+        pub fn $name(&mut self) -> io::Result<$t> {
+            const N: usize = mem::size_of::<$t>();
+            let mut bytes = [0u8; N];
+            if N > self.get_capacity() {
+                // The buffer can't hold whole value, so read it across fills.
+                self.read_exact(&mut bytes)?;
+            } else {
+                bytes.copy_from_slice(&self.fill_buf_exact(N)?[..N]);
+                self.consume(N);
+            }
+            Ok(<$t>::$from(bytes))
+        }
+    };
+}
+
+macro_rules! read_float {
+    ($(#[$attr:meta])* $name:ident, $t:ty, $read_bits:ident) => {
+        $(#[$attr])*
+        ///
+        /// Bits are preserved, including NaN payload.
+        ///
+        /// # Errors
+        ///
+        /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if EOF is reached before enough bytes are read.
+        pub fn $name(&mut self) -> io::Result<$t> {
+            self.$read_bits().map(<$t>::from_bits)
+        }
+    };
+}
+
+macro_rules! read_num_default_endian {
+    ($(#[$attr:meta])* $name:ident, $t:ty, $be:ident, $le:ident) => {
+        $(#[$attr])*
+        ///
+        /// Byte order is set by [`.set_endianness()`](#method.set_endianness).
+        ///
+        /// # Errors
+        ///
+        /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if EOF is reached before enough bytes are read.
+        pub fn $name(&mut self) -> io::Result<$t> {
+            match self.endianness {
+                Endian::Big => self.$be(),
+                Endian::Little => self.$le(),
+            }
+        }
+    };
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Sets byte order used by endianness-neutral numeric methods.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::{Endian, EnsuredBufReader};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0x01, 0x02, 0x01, 0x02];
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.read_u16()?, 0x0102);
+    ///     r.set_endianness(Endian::Little);
+    ///     assert_eq!(r.read_u16()?, 0x0201);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_endianness(&mut self, endianness: Endian) {
+        self.endianness = endianness;
+    }
+
+    /// Get byte order used by endianness-neutral numeric methods.
+    pub fn endianness(&self) -> Endian {
+        self.endianness
+    }
+
+    read_num!(
+        /// Reads an `u8`.
+        read_u8, u8, from_be_bytes
+    );
+    read_num!(
+        /// Reads an `i8`.
+        read_i8, i8, from_be_bytes
+    );
+
+    read_num!(
+        /// Reads a big-endian `u16`.
+        read_u16_be, u16, from_be_bytes
+    );
+    read_num!(
+        /// Reads a little-endian `u16`.
+        read_u16_le, u16, from_le_bytes
+    );
+    read_num!(
+        /// Reads a big-endian `u32`.
+        read_u32_be, u32, from_be_bytes
+    );
+    read_num!(
+        /// Reads a little-endian `u32`.
+        read_u32_le, u32, from_le_bytes
+    );
+    read_num!(
+        /// Reads a big-endian `u64`.
+        read_u64_be, u64, from_be_bytes
+    );
+    read_num!(
+        /// Reads a little-endian `u64`.
+        read_u64_le, u64, from_le_bytes
+    );
+    read_num!(
+        /// Reads a big-endian `i16`.
+        read_i16_be, i16, from_be_bytes
+    );
+    read_num!(
+        /// Reads a little-endian `i16`.
+        read_i16_le, i16, from_le_bytes
+    );
+    read_num!(
+        /// Reads a big-endian `i32`.
+        read_i32_be, i32, from_be_bytes
+    );
+    read_num!(
+        /// Reads a little-endian `i32`.
+        read_i32_le, i32, from_le_bytes
+    );
+    read_num!(
+        /// Reads a big-endian `i64`.
+        read_i64_be, i64, from_be_bytes
+    );
+    read_num!(
+        /// Reads a little-endian `i64`.
+        read_i64_le, i64, from_le_bytes
+    );
+    read_float!(
+        /// Reads a big-endian `f32`.
+        read_f32_be, f32, read_u32_be
+    );
+    read_float!(
+        /// Reads a little-endian `f32`.
+        read_f32_le, f32, read_u32_le
+    );
+    read_float!(
+        /// Reads a big-endian `f64`.
+        read_f64_be, f64, read_u64_be
+    );
+    read_float!(
+        /// Reads a little-endian `f64`.
+        read_f64_le, f64, read_u64_le
+    );
+
+    /// Reads a `bool` from a byte, `0` as `false` and `1` as `true`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if EOF is reached.
+    /// Returns error that has `.kind() == ErrorKind::InvalidData` if the byte is neither `0` nor `1`.
+    /// In this case, the byte is not consumed.
+    pub fn read_bool(&mut self) -> io::Result<bool> {
+        let value = match self.fill_buf_exact(1)?[0] {
+            0 => false,
+            1 => true,
+            b => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid bool value: {}.", b),
+                ))
+            }
+        };
+        self.consume(1);
+        Ok(value)
+    }
+
+    /// Reads a string prefixed with its length in bytes as big-endian `u16`.
+    ///
+    /// See [`.read_string_u32_be()`](#method.read_string_u32_be) for details.
+    pub fn read_string_u16_be(&mut self, max_len: usize) -> io::Result<String> {
+        let len = u64::from(self.read_u16_be()?);
+        let bytes = self.read_string_bytes(len, max_len)?;
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Reads a string prefixed with its length in bytes as big-endian `u32`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if EOF is reached before the whole string is read.
+    /// Returns error that has `.kind() == ErrorKind::InvalidData` if the length is larger than `max_len`
+    /// (wrapping [`RecordTooLongError`](struct.RecordTooLongError.html)), or the string is not valid UTF-8.
+    /// In these cases, bytes read so far are consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"\x00\x00\x00\x05hello";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.read_string_u32_be(16)?, "hello");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_string_u32_be(&mut self, max_len: usize) -> io::Result<String> {
+        let len = u64::from(self.read_u32_be()?);
+        let bytes = self.read_string_bytes(len, max_len)?;
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Reads a string prefixed with its length in bytes as big-endian `u16`, replacing invalid UTF-8 with `U+FFFD`.
+    ///
+    /// See [`.read_string_u32_be()`](#method.read_string_u32_be) for details.
+    pub fn read_string_u16_be_lossy(&mut self, max_len: usize) -> io::Result<String> {
+        let len = u64::from(self.read_u16_be()?);
+        let bytes = self.read_string_bytes(len, max_len)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Reads a string prefixed with its length in bytes as big-endian `u32`, replacing invalid UTF-8 with `U+FFFD`.
+    ///
+    /// See [`.read_string_u32_be()`](#method.read_string_u32_be) for details.
+    pub fn read_string_u32_be_lossy(&mut self, max_len: usize) -> io::Result<String> {
+        let len = u64::from(self.read_u32_be()?);
+        let bytes = self.read_string_bytes(len, max_len)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn read_string_bytes(&mut self, len: u64, max_len: usize) -> io::Result<Vec<u8>> {
+        if len > max_len as u64 {
+            return Err(RecordTooLongError::new(max_len).into_io_error());
+        }
+        let mut bytes = vec![0u8; len as usize];
+        self.read_exact_bypassing(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    read_num_default_endian!(
+        /// Reads an `u16`.
+        read_u16, u16, read_u16_be, read_u16_le
+    );
+    read_num_default_endian!(
+        /// Reads an `u32`.
+        read_u32, u32, read_u32_be, read_u32_le
+    );
+    read_num_default_endian!(
+        /// Reads an `u64`.
+        read_u64, u64, read_u64_be, read_u64_le
+    );
+    read_num_default_endian!(
+        /// Reads an `i16`.
+        read_i16, i16, read_i16_be, read_i16_le
+    );
+    read_num_default_endian!(
+        /// Reads an `i32`.
+        read_i32, i32, read_i32_be, read_i32_le
+    );
+    read_num_default_endian!(
+        /// Reads an `i64`.
+        read_i64, i64, read_i64_be, read_i64_le
+    );
+    read_num_default_endian!(
+        /// Reads an `f32`.
+        read_f32, f32, read_f32_be, read_f32_le
+    );
+    read_num_default_endian!(
+        /// Reads an `f64`.
+        read_f64, f64, read_f64_be, read_f64_le
+    );
+}
@@ -0,0 +1,84 @@
+use std::io::Read;
+
+use crate::EnsuredBufReader;
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Records a checkpoint at `offset` in the stream, which consuming must land on exactly.
+    ///
+    /// After this, `consume()` (and methods consuming bytes) panics if it would move [`.position()`](#method.position)
+    /// past `offset` without stopping at `offset`. A checkpoint is removed once it is reached.
+    /// Multiple checkpoints can be recorded in any order.
+    ///
+    /// This is a debugging aid to catch framing bugs of parsers early, e.g. by recording end of each record.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is before current position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"\x03abc\x02de";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let len = r.read_u8()?;
+    ///     r.push_checkpoint(r.position() + u64::from(len));
+    ///     let mut record = vec![0u8; len as usize];
+    ///     r.read_exact(&mut record)?;
+    ///     assert_eq!(record, b"abc");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn push_checkpoint(&mut self, offset: u64) {
+        assert!(
+            offset >= self.position,
+            "'offset' ({}) must not be before current position ({}).",
+            offset,
+            self.position
+        );
+        if offset == self.position {
+            return;
+        }
+        // Sorted in descending order, so the next checkpoint is the last.
+        let i = self
+            .checkpoints
+            .iter()
+            .position(|&c| c <= offset)
+            .unwrap_or(self.checkpoints.len());
+        self.checkpoints.insert(i, offset);
+    }
+
+    // Advances position by `amt`, verifying checkpoints.
+    pub(crate) fn advance_position(&mut self, amt: u64) {
+        let new_position = self.position + amt;
+        while let Some(&next) = self.checkpoints.last() {
+            if next > new_position {
+                break;
+            }
+            assert!(
+                next == new_position,
+                "consumed across checkpoint at {} (position {} -> {}).",
+                next,
+                self.position,
+                new_position
+            );
+            self.checkpoints.pop();
+            if self.mark.is_some() {
+                self.reached_checkpoints.push(next);
+            }
+        }
+        self.position = new_position;
+    }
+}
+
+// Restores checkpoints in `reached` after moving back to `position`.
+pub(crate) fn restore_checkpoints(checkpoints: &mut Vec<u64>, reached: &[u64], position: u64) {
+    checkpoints.extend(reached.iter().filter(|&&c| c > position));
+    // Sorted in descending order, as `push_checkpoint()` does.
+    checkpoints.sort_unstable_by(|a, b| b.cmp(a));
+    checkpoints.dedup();
+}
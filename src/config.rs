@@ -0,0 +1,173 @@
+use std::io::{self, Read};
+
+use crate::{check_sizes, Endian, EnsuredBufReader, DEFAULT_BUFFER_SIZE, DEFAULT_ENSURED_BYTES};
+
+/// Settings to build `EnsuredBufReader` by [`from_config()`](struct.EnsuredBufReader.html#method.from_config).
+///
+/// This gathers settings in one value, so it can be loaded from configuration files and applied to readers uniformly.
+/// Fields not listed here keep their defaults.
+///
+/// With `serde` feature, this implements `Serialize` and `Deserialize`.
+/// Missing fields are deserialized as defaults.
+///
+/// New fields may be added in future, so this can't be built by struct literal.
+/// Start from `ReaderConfig::default()` and change fields, or use setters of the same name.
+///
+/// # Examples
+///
+/// ```
+/// use ensured_bufreader::{Endian, EnsuredBufReader, ReaderConfig};
+///
+/// fn main() -> std::io::Result<()> {
+///     let config = ReaderConfig::default()
+///         .ensured_size(32)
+///         .endianness(Endian::Little);
+///
+///     let input: &[u8] = &[0x01, 0x02];
+///     let mut r = EnsuredBufReader::from_config(config, input)?;
+///     assert_eq!(r.read_u16()?, 0x0201);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ReaderConfig {
+    /// Initial _capacity_. Default is [`DEFAULT_BUFFER_SIZE`](constant.DEFAULT_BUFFER_SIZE.html).
+    pub capacity: usize,
+    /// _Ensured_ size. Default is [`DEFAULT_ENSURED_BYTES`](constant.DEFAULT_ENSURED_BYTES.html).
+    pub ensured_size: usize,
+    /// See [`.set_auto_grow()`](struct.EnsuredBufReader.html#method.set_auto_grow). Default is `false`.
+    pub auto_grow: bool,
+    /// See [`.set_max_capacity()`](struct.EnsuredBufReader.html#method.set_max_capacity). Default is `None`, unbounded.
+    pub max_capacity: Option<usize>,
+    /// See [`.set_fill_to_capacity()`](struct.EnsuredBufReader.html#method.set_fill_to_capacity). Default is `false`.
+    pub fill_to_capacity: bool,
+    /// See [`.set_max_read_chunk()`](struct.EnsuredBufReader.html#method.set_max_read_chunk). Default is `None`, unlimited.
+    pub max_read_chunk: Option<usize>,
+    /// See [`.set_strict_eof()`](struct.EnsuredBufReader.html#method.set_strict_eof). Default is `true`.
+    pub strict_eof: bool,
+    /// See [`.set_max_record_size()`](struct.EnsuredBufReader.html#method.set_max_record_size). Default is `None`, unlimited.
+    pub max_record_size: Option<usize>,
+    /// See [`.set_endianness()`](struct.EnsuredBufReader.html#method.set_endianness). Default is `Endian::Big`.
+    pub endianness: Endian,
+    // Prevents struct literal, so that adding fields is not a breaking change.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    _priv: (),
+}
+
+impl Default for ReaderConfig {
+    fn default() -> ReaderConfig {
+        ReaderConfig {
+            capacity: DEFAULT_BUFFER_SIZE,
+            ensured_size: DEFAULT_ENSURED_BYTES,
+            auto_grow: false,
+            max_capacity: None,
+            fill_to_capacity: false,
+            max_read_chunk: None,
+            strict_eof: true,
+            max_record_size: None,
+            endianness: Endian::default(),
+            _priv: (),
+        }
+    }
+}
+
+impl ReaderConfig {
+    /// Sets [`capacity`](#structfield.capacity).
+    pub fn capacity(mut self, capacity: usize) -> ReaderConfig {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets [`ensured_size`](#structfield.ensured_size).
+    pub fn ensured_size(mut self, ensured_size: usize) -> ReaderConfig {
+        self.ensured_size = ensured_size;
+        self
+    }
+
+    /// Sets [`auto_grow`](#structfield.auto_grow).
+    pub fn auto_grow(mut self, auto_grow: bool) -> ReaderConfig {
+        self.auto_grow = auto_grow;
+        self
+    }
+
+    /// Sets [`max_capacity`](#structfield.max_capacity).
+    pub fn max_capacity(mut self, max_capacity: Option<usize>) -> ReaderConfig {
+        self.max_capacity = max_capacity;
+        self
+    }
+
+    /// Sets [`fill_to_capacity`](#structfield.fill_to_capacity).
+    pub fn fill_to_capacity(mut self, fill_to_capacity: bool) -> ReaderConfig {
+        self.fill_to_capacity = fill_to_capacity;
+        self
+    }
+
+    /// Sets [`max_read_chunk`](#structfield.max_read_chunk).
+    pub fn max_read_chunk(mut self, max_read_chunk: Option<usize>) -> ReaderConfig {
+        self.max_read_chunk = max_read_chunk;
+        self
+    }
+
+    /// Sets [`strict_eof`](#structfield.strict_eof).
+    pub fn strict_eof(mut self, strict_eof: bool) -> ReaderConfig {
+        self.strict_eof = strict_eof;
+        self
+    }
+
+    /// Sets [`max_record_size`](#structfield.max_record_size).
+    pub fn max_record_size(mut self, max_record_size: Option<usize>) -> ReaderConfig {
+        self.max_record_size = max_record_size;
+        self
+    }
+
+    /// Sets [`endianness`](#structfield.endianness).
+    pub fn endianness(mut self, endianness: Endian) -> ReaderConfig {
+        self.endianness = endianness;
+        self
+    }
+}
+
+impl<R: Read> EnsuredBufReader<R, Vec<u8>> {
+    /// Creates a new `EnsuredBufReader` with settings of `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` and wraps [`ConfigError`](enum.ConfigError.html)
+    /// if `capacity` and `ensured_size` are invalid as [`.set_ensured_size()`](#method.set_ensured_size).
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `max_read_chunk` or `max_record_size` is `Some(0)`.
+    pub fn from_config(config: ReaderConfig, inner: R) -> io::Result<EnsuredBufReader<R, Vec<u8>>> {
+        check_sizes(config.capacity, config.ensured_size)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        if config.max_read_chunk == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "'max_read_chunk' must be positive.",
+            ));
+        }
+        if config.max_record_size == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "'max_record_size' must be positive.",
+            ));
+        }
+
+        let mut r =
+            EnsuredBufReader::from_raw(inner, vec![0; config.capacity], config.ensured_size);
+        if config.auto_grow {
+            r.set_auto_grow(true);
+        }
+        if let Some(max_capacity) = config.max_capacity {
+            r.set_max_capacity(max_capacity);
+        }
+        r.set_fill_to_capacity(config.fill_to_capacity);
+        if let Some(max_read_chunk) = config.max_read_chunk {
+            r.set_max_read_chunk(max_read_chunk);
+        }
+        r.set_strict_eof(config.strict_eof);
+        r.set_max_record_size(config.max_record_size);
+        r.set_endianness(config.endianness);
+        Ok(r)
+    }
+}
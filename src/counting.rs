@@ -0,0 +1,63 @@
+use std::io::{self, Read};
+
+/// A reader that counts bytes and calls of `read()` passed to underlying reader.
+///
+/// Wrap underlying reader with this before giving it to `EnsuredBufReader`, to observe how it is read.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// use ensured_bufreader::{CountingReader, EnsuredBufReader};
+///
+/// fn main() -> std::io::Result<()> {
+///     let input: &[u8] = &[0u8; 1000];
+///     let mut counter = CountingReader::new(input);
+///
+///     EnsuredBufReader::new(&mut counter).read_to_end(&mut Vec::new())?;
+///     assert_eq!(counter.bytes_read(), 1000);
+///     assert!(counter.read_calls() >= 1);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CountingReader<R> {
+    inner: R,
+    bytes_read: u64,
+    read_calls: u64,
+}
+
+impl<R: Read> CountingReader<R> {
+    /// Creates a new `CountingReader`.
+    pub fn new(inner: R) -> CountingReader<R> {
+        CountingReader {
+            inner,
+            bytes_read: 0,
+            read_calls: 0,
+        }
+    }
+
+    /// Returns count of bytes read from underlying reader.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns count of `read()` calls, including failed ones.
+    pub fn read_calls(&self) -> u64 {
+        self.read_calls
+    }
+
+    /// Returns underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_calls += 1;
+        let n = self.inner.read(buf)?;
+        self.bytes_read += n as u64;
+        Ok(n)
+    }
+}
@@ -0,0 +1,563 @@
+use std::cmp;
+use std::io::{self, BufRead, Read};
+
+use crate::{EnsuredBufReader, RecordTooLongError};
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Sets max length of a record read by delimiter-based methods.
+    ///
+    /// `read_until()`, `read_line()`, [`.read_until_slice()`](#method.read_until_slice), [`.read_until_u16_le()`](#method.read_until_u16_le),
+    /// [`.read_until_u16_be()`](#method.read_until_u16_be) and methods built on them, such as `lines()` and `split()`, respect this limit.
+    /// Length of a record includes its delimiter.
+    /// If a record is longer than the limit, they return error that has `.kind() == ErrorKind::InvalidData`
+    /// and wraps [`RecordTooLongError`](struct.RecordTooLongError.html).
+    /// In this case, first `max` bytes of the record are appended and consumed.
+    /// If `read_line()` cuts a UTF-8 character at `max`, the character is consumed but not appended.
+    ///
+    /// Limits given to each call, such as `max` of [`.read_line_bounded()`](#method.read_line_bounded)
+    /// or length of `out` of [`.read_until_into()`](#method.read_until_into), take precedence over this.
+    /// [`.process_line()`](#method.process_line) doesn't store the line, so it is not limited.
+    /// Default is `None`, unlimited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is `Some(0)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, ErrorKind};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"short\nvery long line\n";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///     r.set_max_record_size(Some(8));
+    ///
+    ///     let mut line = String::new();
+    ///     r.read_line(&mut line)?;
+    ///     assert_eq!(line, "short\n");
+    ///
+    ///     let err = r.read_line(&mut line).unwrap_err();
+    ///     assert_eq!(err.kind(), ErrorKind::InvalidData);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_max_record_size(&mut self, max: Option<usize>) {
+        assert_ne!(max, Some(0), "'max' must be positive.");
+        self.max_record_size = max;
+    }
+
+    // Limits `used` bytes to the record limit, with `read` bytes of the record already read.
+    // Returns the limited count and whether the record is too long.
+    fn limit_record(&self, read: usize, used: usize) -> (usize, bool) {
+        match self.max_record_size {
+            Some(max) if read + used > max => (max - read, true),
+            _ => (used, false),
+        }
+    }
+
+    fn record_too_long(&self) -> io::Error {
+        RecordTooLongError::new(self.max_record_size.unwrap_or(0)).into_io_error()
+    }
+
+    pub(crate) fn read_until_limited(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let start = buf.len();
+        loop {
+            let (found, used, too_long) = {
+                let available = self.fill_buf()?;
+                if available.is_empty() {
+                    return Ok(buf.len() - start);
+                }
+                let (found, used) = match find_byte(byte, available) {
+                    Some(i) => (true, i + 1),
+                    None => (false, available.len()),
+                };
+                let (used, too_long) = self.limit_record(buf.len() - start, used);
+                buf.extend_from_slice(&self.buffer()[..used]);
+                (found, used, too_long)
+            };
+            self.consume(used);
+            if too_long {
+                return Err(self.record_too_long());
+            }
+            if found {
+                return Ok(buf.len() - start);
+            }
+        }
+    }
+
+    /// Returns bytes up to and including `byte` without copying, if `byte` is in the buffer after `fill_buf()`.
+    ///
+    /// Returned slice is not consumed. Call `consume()` with its length to advance.
+    /// Returns `None` if `byte` is not found in the buffer, either because EOF is reached or the record is longer than the buffer.
+    /// In that case, fall back to `read_until()`.
+    ///
+    /// The returned slice borrows the buffer, so it is invalidated by the next fill.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo,bar";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let n = {
+    ///         let record = r.read_until_borrowed(b',')?.unwrap();
+    ///         assert_eq!(record, b"foo,");
+    ///         record.len()
+    ///     };
+    ///     r.consume(n);
+    ///
+    ///     assert_eq!(r.read_until_borrowed(b',')?, None);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_until_borrowed(&mut self, byte: u8) -> io::Result<Option<&[u8]>> {
+        let buf = self.fill_buf()?;
+        Ok(find_byte(byte, buf).map(|i| &buf[..=i]))
+    }
+
+    /// Reads bytes into `out` until `byte` or EOF is reached, without allocation.
+    ///
+    /// Returns count of bytes written to `out`, including `byte`, and whether `byte` is found.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidData` and wraps [`RecordTooLongError`](struct.RecordTooLongError.html),
+    /// if the record including `byte` is longer than `out`.
+    /// In this case, `out` is filled with head of the record, and those bytes are consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo\nbar";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///     let mut out = [0u8; 8];
+    ///
+    ///     assert_eq!(r.read_until_into(b'\n', &mut out)?, (4, true));
+    ///     assert_eq!(&out[..4], b"foo\n");
+    ///
+    ///     assert_eq!(r.read_until_into(b'\n', &mut out)?, (3, false));
+    ///     assert_eq!(&out[..3], b"bar");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_until_into(&mut self, byte: u8, out: &mut [u8]) -> io::Result<(usize, bool)> {
+        let mut written = 0;
+        loop {
+            let (found, len, n) = {
+                let available = self.fill_buf()?;
+                if available.is_empty() {
+                    return Ok((written, false));
+                }
+                let (found, len) = match find_byte(byte, available) {
+                    Some(i) => (true, i + 1),
+                    None => (false, available.len()),
+                };
+                let n = cmp::min(len, out.len() - written);
+                out[written..written + n].copy_from_slice(&available[..n]);
+                (found, len, n)
+            };
+            self.consume(n);
+            written += n;
+            if n < len {
+                return Err(RecordTooLongError::new(out.len()).into_io_error());
+            }
+            if found {
+                return Ok((written, true));
+            }
+        }
+    }
+
+    /// Reads bytes into `buf` until the separator `sep` or EOF is reached.
+    ///
+    /// This is multi-byte version of `read_until()`.
+    /// Bytes up to and including `sep` are appended to `buf`. Returns count of appended bytes.
+    ///
+    /// Separator is searched by Knuth-Morris-Pratt algorithm, and partial match is carried across fills.
+    /// So this runs in linear time, even for long records and repetitive separators such as `b"\r\n\r\n"`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sep` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo\r\nbar";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let mut buf = Vec::new();
+    ///     assert_eq!(r.read_until_slice(b"\r\n", &mut buf)?, 5);
+    ///     assert_eq!(buf, b"foo\r\n");
+    ///
+    ///     buf.clear();
+    ///     assert_eq!(r.read_until_slice(b"\r\n", &mut buf)?, 3);
+    ///     assert_eq!(buf, b"bar");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_until_slice(&mut self, sep: &[u8], buf: &mut Vec<u8>) -> io::Result<usize> {
+        assert!(!sep.is_empty(), "'sep' must not be empty.");
+
+        let table = failure_table(sep);
+        let start = buf.len();
+        let mut matched = 0;
+        loop {
+            let (found, used, too_long) = {
+                let available = self.fill_buf()?;
+                if available.is_empty() {
+                    return Ok(buf.len() - start);
+                }
+
+                let mut found = None;
+                for (i, &b) in available.iter().enumerate() {
+                    matched = next_state(sep, &table, matched, b);
+                    if matched == sep.len() {
+                        found = Some(i + 1);
+                        break;
+                    }
+                }
+                let used = found.unwrap_or(available.len());
+                let (used, too_long) = self.limit_record(buf.len() - start, used);
+                buf.extend_from_slice(&self.buffer()[..used]);
+                (found.is_some(), used, too_long)
+            };
+            self.consume(used);
+            if too_long {
+                return Err(self.record_too_long());
+            }
+            if found {
+                return Ok(buf.len() - start);
+            }
+        }
+    }
+
+    /// Reads bytes into `buf` until little-endian 16-bit `unit` or EOF is reached.
+    ///
+    /// This is `read_until()` for 16-bit encodings such as UTF-16LE.
+    /// `unit` is searched only on 2-byte aligned offsets counted from [`.position()`](#method.position) 0,
+    /// so a byte of `unit` in another code unit doesn't match.
+    /// Bytes up to and including `unit` are appended to `buf`. Returns count of appended bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     // "\u{0a0a}\n" in UTF-16LE
+    ///     let input: &[u8] = b"\x0a\x0a\x0a\x00";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let mut buf = Vec::new();
+    ///     assert_eq!(r.read_until_u16_le(0x000a, &mut buf)?, 4);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_until_u16_le(&mut self, unit: u16, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.read_until_unit(unit.to_le_bytes(), buf)
+    }
+
+    /// Reads bytes into `buf` until big-endian 16-bit `unit` or EOF is reached.
+    ///
+    /// This is `read_until()` for 16-bit encodings such as UTF-16BE.
+    /// See [`.read_until_u16_le()`](#method.read_until_u16_le) for details.
+    pub fn read_until_u16_be(&mut self, unit: u16, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.read_until_unit(unit.to_be_bytes(), buf)
+    }
+
+    fn read_until_unit(&mut self, unit: [u8; 2], buf: &mut Vec<u8>) -> io::Result<usize> {
+        let start = buf.len();
+        loop {
+            // Leading byte is the second half of a unit.
+            let odd = self.position() % 2 == 1;
+            let (found, used, too_long) = {
+                let available = self.fill_buf_to_expected_size(2)?;
+                if available.is_empty() {
+                    return Ok(buf.len() - start);
+                }
+
+                let mut i = if odd { 1 } else { 0 };
+                let mut found = false;
+                while i + 2 <= available.len() {
+                    i += 2;
+                    if available[i - 2..i] == unit {
+                        found = true;
+                        break;
+                    }
+                }
+                // Only 1 byte is available at EOF.
+                let used = if i == 0 { available.len() } else { i };
+                let (used, too_long) = self.limit_record(buf.len() - start, used);
+                buf.extend_from_slice(&self.buffer()[..used]);
+                (found, used, too_long)
+            };
+            self.consume(used);
+            if too_long {
+                return Err(self.record_too_long());
+            }
+            if found {
+                return Ok(buf.len() - start);
+            }
+        }
+    }
+
+    /// Consumes consecutive `byte`s from current position, and returns count of them.
+    ///
+    /// Stops at the first byte other than `byte`, which is left buffered, or EOF.
+    /// This is useful to strip padding such as leading zeros or spaces of fixed-width formats.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"   42";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.skip_byte_run(b' ')?, 3);
+    ///     assert_eq!(r.fill_buf()?, b"42");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn skip_byte_run(&mut self, byte: u8) -> io::Result<usize> {
+        let mut skipped = 0;
+        loop {
+            let (used, done) = {
+                let available = self.fill_buf()?;
+                if available.is_empty() {
+                    return Ok(skipped);
+                }
+                match available.iter().position(|&b| b != byte) {
+                    Some(i) => (i, true),
+                    None => (available.len(), false),
+                }
+            };
+            self.consume(used);
+            skipped += used;
+            if done {
+                return Ok(skipped);
+            }
+        }
+    }
+
+    /// Returns an iterator over chunks of the stream separated by `delim`, without `delim`.
+    ///
+    /// This is same as `split()`, but respects [`.set_max_record_size()`](#method.set_max_record_size).
+    /// Consecutive delimiters yield an empty chunk, and the last chunk is yielded even if it doesn't end with `delim`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo\0bar";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let chunks = r.split_on(0).collect::<std::io::Result<Vec<_>>>()?;
+    ///     assert_eq!(chunks, vec![b"foo".to_vec(), b"bar".to_vec()]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn split_on(&mut self, delim: u8) -> SplitOn<'_, R, B> {
+        SplitOn {
+            reader: self,
+            delim,
+        }
+    }
+
+    /// Returns a splitter over segments of the stream separated by `delim`.
+    ///
+    /// Unlike `split()`, segments are borrowed from the buffer when the whole segment fits in the buffer.
+    /// Otherwise, the segment is copied into a `Vec<u8>` reused by the splitter.
+    ///
+    /// See [`SplitBorrowed`](struct.SplitBorrowed.html) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo,bar,baz";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let mut split = r.split_borrowed(b',');
+    ///     assert_eq!(split.next()?, Some(&b"foo"[..]));
+    ///     assert_eq!(split.next()?, Some(&b"bar"[..]));
+    ///     assert_eq!(split.next()?, Some(&b"baz"[..]));
+    ///     assert_eq!(split.next()?, None);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn split_borrowed(&mut self, delim: u8) -> SplitBorrowed<'_, R, B> {
+        SplitBorrowed {
+            reader: self,
+            delim,
+            segment: Vec::new(),
+            pending: 0,
+        }
+    }
+}
+
+/// An iterator over chunks separated by a delimiter.
+///
+/// This struct is created by [`.split_on()`](struct.EnsuredBufReader.html#method.split_on).
+pub struct SplitOn<'a, R, B>
+where
+    R: Read,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    reader: &'a mut EnsuredBufReader<R, B>,
+    delim: u8,
+}
+
+impl<'a, R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Iterator for SplitOn<'a, R, B> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<io::Result<Vec<u8>>> {
+        let mut chunk = Vec::new();
+        match self.reader.read_until_limited(self.delim, &mut chunk) {
+            Ok(0) => None,
+            Ok(_) => {
+                if chunk.last() == Some(&self.delim) {
+                    chunk.pop();
+                }
+                Some(Ok(chunk))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Splitter over segments separated by a delimiter, borrowing them from the buffer if possible.
+///
+/// This struct is created by [`.split_borrowed()`](struct.EnsuredBufReader.html#method.split_borrowed).
+///
+/// This is not an `Iterator`, because each segment borrows the splitter and is invalidated by next call of [`.next()`](#method.next).
+/// A segment returned by `.next()` is consumed from the reader at next call or drop of the splitter.
+pub struct SplitBorrowed<'a, R, B>
+where
+    R: Read,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    reader: &'a mut EnsuredBufReader<R, B>,
+    delim: u8,
+    segment: Vec<u8>,
+    pending: usize,
+}
+
+impl<'a, R: Read, B: AsRef<[u8]> + AsMut<[u8]>> SplitBorrowed<'a, R, B> {
+    /// Returns next segment without delimiter, or `None` at EOF.
+    ///
+    /// If the segment is not in the buffer after the buffer is filled to _capacity_,
+    /// it is read by `read_until()` into internal `Vec<u8>`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidData` and wraps [`RecordTooLongError`](struct.RecordTooLongError.html)
+    /// if the segment is longer than [`.set_max_record_size()`](struct.EnsuredBufReader.html#method.set_max_record_size).
+    /// As `read_until()`, first `max` bytes of the segment are consumed in this case.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> io::Result<Option<&[u8]>> {
+        self.reader.consume(self.pending);
+        self.pending = 0;
+
+        let capacity = self.reader.get_capacity();
+        let delim = self.delim;
+        let (found, available) = {
+            let mut buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            if !buf.contains(&delim) && buf.len() < capacity {
+                buf = self.reader.fill_buf_to_expected_size(capacity)?;
+            }
+            (find_byte(delim, buf), buf.len())
+        };
+
+        // The whole record is in the buffer if the delimiter is found, or if the last segment ends at EOF.
+        let record = match found {
+            Some(i) => Some((i + 1, i)),
+            None if self.reader.eof => Some((available, available)),
+            None => None,
+        };
+        match record {
+            Some((record, segment)) => {
+                let (used, too_long) = self.reader.limit_record(0, record);
+                if too_long {
+                    self.reader.consume(used);
+                    return Err(self.reader.record_too_long());
+                }
+                self.pending = record;
+                Ok(Some(&self.reader.buffer()[..segment]))
+            }
+            None => {
+                self.segment.clear();
+                self.reader.read_until(delim, &mut self.segment)?;
+                if self.segment.last() == Some(&delim) {
+                    self.segment.pop();
+                }
+                Ok(Some(&self.segment))
+            }
+        }
+    }
+}
+
+impl<'a, R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Drop for SplitBorrowed<'a, R, B> {
+    fn drop(&mut self) {
+        self.reader.consume(self.pending);
+    }
+}
+
+// Returns index of the first `byte` in `haystack`.
+#[cfg(feature = "memchr")]
+pub(crate) fn find_byte(byte: u8, haystack: &[u8]) -> Option<usize> {
+    memchr::memchr(byte, haystack)
+}
+
+// Returns index of the first `byte` in `haystack`.
+#[cfg(not(feature = "memchr"))]
+pub(crate) fn find_byte(byte: u8, haystack: &[u8]) -> Option<usize> {
+    haystack.iter().position(|&b| b == byte)
+}
+
+// Knuth-Morris-Pratt failure table: `table[i]` is length of the longest proper prefix of `sep[..=i]` that is also its suffix.
+fn failure_table(sep: &[u8]) -> Vec<usize> {
+    let mut table = vec![0; sep.len()];
+    let mut k = 0;
+    for i in 1..sep.len() {
+        k = next_state(sep, &table, k, sep[i]);
+        table[i] = k;
+    }
+    table
+}
+
+// Returns count of matched bytes of `sep` after reading `b` with `matched` bytes matched.
+fn next_state(sep: &[u8], table: &[usize], mut matched: usize, b: u8) -> usize {
+    if matched == sep.len() {
+        matched = table[matched - 1];
+    }
+    while matched > 0 && sep[matched] != b {
+        matched = table[matched - 1];
+    }
+    if sep[matched] == b {
+        matched + 1
+    } else {
+        0
+    }
+}
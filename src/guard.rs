@@ -0,0 +1,116 @@
+use std::cmp;
+use std::io::{self, BufRead, Read};
+
+use crate::DEFAULT_ENSURED_BYTES;
+
+/// A [`BufRead`](https://doc.rust-lang.org/std/io/trait.BufRead.html)er that adds _ensured_ guarantee to another `BufRead`er.
+///
+/// Unlike `EnsuredBufReader`, this doesn't own a buffer of _capacity_.
+/// If underlying reader returns at least _ensured_ bytes from `fill_buf()`, they are passed through without copying.
+/// Only if it returns fewer bytes, they are copied into a small accumulator and topped up to _ensured_ size.
+///
+/// This is useful to avoid double buffering over a reader that is already buffered, such as `StdinLock`.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{BufRead, BufReader};
+/// use ensured_bufreader::EnsuredGuard;
+///
+/// fn main() -> std::io::Result<()> {
+///     let input: &[u8] = b"0123456789";
+///     let inner = BufReader::with_capacity(3, input);
+///     let mut r = EnsuredGuard::with_ensured_size(8, inner);
+///
+///     assert_eq!(r.fill_buf()?, b"01234567");
+///     Ok(())
+/// }
+/// ```
+pub struct EnsuredGuard<R: BufRead> {
+    inner: R,
+    ensured_size: usize,
+    acc: Vec<u8>,
+    acc_pos: usize,
+}
+
+impl<R: BufRead> EnsuredGuard<R> {
+    /// Creates a new `EnsuredGuard` with a default _ensured_ size (`DEFAULT_ENSURED_BYTES`).
+    pub fn new(inner: R) -> EnsuredGuard<R> {
+        EnsuredGuard::with_ensured_size(DEFAULT_ENSURED_BYTES, inner)
+    }
+
+    /// Creates a new `EnsuredGuard` with a specified `ensured_size`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ensured_size` is 0.
+    pub fn with_ensured_size(ensured_size: usize, inner: R) -> EnsuredGuard<R> {
+        assert_ne!(ensured_size, 0, "'ensure' must be positive.");
+        EnsuredGuard {
+            inner,
+            ensured_size,
+            acc: Vec::new(),
+            acc_pos: 0,
+        }
+    }
+
+    /// Returns _ensured_ size.
+    pub fn get_ensured_size(&self) -> usize {
+        self.ensured_size
+    }
+
+    // Moves bytes from underlying reader into the accumulator until it holds _ensured_ bytes or EOF.
+    fn top_up(&mut self) -> io::Result<()> {
+        // Accumulator holds at most ensured size, so this is cheap.
+        self.acc.drain(..self.acc_pos);
+        self.acc_pos = 0;
+        while self.acc.len() < self.ensured_size {
+            let need = self.ensured_size - self.acc.len();
+            let n = {
+                let buf = self.inner.fill_buf()?;
+                if buf.is_empty() {
+                    break;
+                }
+                let n = cmp::min(need, buf.len());
+                self.acc.extend_from_slice(&buf[..n]);
+                n
+            };
+            self.inner.consume(n);
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for EnsuredGuard<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for EnsuredGuard<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.acc_pos == self.acc.len() {
+            let len = self.inner.fill_buf()?.len();
+            if len >= self.ensured_size || len == 0 {
+                // `fill_buf()` of buffered bytes doesn't read again.
+                return self.inner.fill_buf();
+            }
+        }
+        self.top_up()?;
+        Ok(&self.acc[self.acc_pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.acc_pos == self.acc.len() {
+            self.inner.consume(amt);
+            return;
+        }
+        assert!(
+            amt <= self.acc.len() - self.acc_pos,
+            "the amt must be <= the number of bytes in the buffer returned by fill_buf."
+        );
+        self.acc_pos += amt;
+    }
+}
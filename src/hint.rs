@@ -0,0 +1,65 @@
+use std::io::Read;
+
+use crate::EnsuredBufReader;
+
+/// A reader that reports its preferred size of a single `read()`.
+///
+/// This is for sources sensitive to alignment or block size, such as block devices.
+/// Enable it by [`.set_use_read_hint()`](struct.EnsuredBufReader.html#method.set_use_read_hint).
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{self, BufRead, Read};
+/// use ensured_bufreader::{EnsuredBufReader, ReadHint};
+///
+/// struct Device<'a>(&'a [u8]);
+///
+/// impl<'a> Read for Device<'a> {
+///     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+///         assert_eq!(buf.len() % 512, 0);
+///         self.0.read(buf)
+///     }
+/// }
+///
+/// impl<'a> ReadHint for Device<'a> {
+///     fn preferred_read_size(&self) -> Option<usize> {
+///         Some(512)
+///     }
+/// }
+///
+/// fn main() -> std::io::Result<()> {
+///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(2000, 1000, Device(&[0; 4096]));
+///     r.set_use_read_hint(true);
+///
+///     assert_eq!(r.fill_buf()?.len(), 1536);
+///     Ok(())
+/// }
+/// ```
+pub trait ReadHint {
+    /// Returns preferred read size, or `None` if there is no preference.
+    ///
+    /// Length of each `read()` is rounded down to a multiple of this if it is not smaller than this.
+    /// `Some(0)` is treated as `None`.
+    fn preferred_read_size(&self) -> Option<usize>;
+}
+
+impl<T: ReadHint + ?Sized> ReadHint for &mut T {
+    fn preferred_read_size(&self) -> Option<usize> {
+        (**self).preferred_read_size()
+    }
+}
+
+impl<R: Read + ReadHint, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Sets whether to size each read of underlying reader by [`ReadHint`](trait.ReadHint.html).
+    ///
+    /// Read size is still limited by [`.set_max_read_chunk()`](#method.set_max_read_chunk) and free space of the buffer.
+    /// Default is `false`.
+    pub fn set_use_read_hint(&mut self, enabled: bool) {
+        self.read_hint = if enabled {
+            Some(<R as ReadHint>::preferred_read_size)
+        } else {
+            None
+        };
+    }
+}
@@ -3,9 +3,50 @@
 //!
 
 #![warn(missing_docs)]
+#![cfg_attr(feature = "read_buf", feature(read_buf, core_io_borrowed_buf))]
+use std::cmp;
 use std::error;
 use std::fmt;
 use std::io::{self, BufRead, Read};
+use std::num::NonZeroUsize;
+
+#[cfg(feature = "const_generics")]
+mod array;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod binary;
+mod checkpoint;
+mod config;
+mod counting;
+mod delimited;
+mod guard;
+mod hint;
+mod lines;
+mod on_drop;
+mod seek;
+mod state;
+mod take;
+#[cfg(feature = "testutil")]
+pub mod testutil;
+#[cfg(feature = "tokio")]
+mod tokio_io;
+mod trailer;
+mod transaction;
+
+pub use binary::Endian;
+pub use config::ReaderConfig;
+pub use counting::CountingReader;
+pub use delimited::{SplitBorrowed, SplitOn};
+pub use guard::EnsuredGuard;
+pub use hint::ReadHint;
+pub use lines::{ByteLines, LineOutcome, LineTerminator, Utf8LineError};
+pub use on_drop::{DropGuard, DropHook, FinalStats};
+pub use state::ReaderState;
+pub use take::EnsuredTake;
+#[cfg(feature = "tokio")]
+pub use tokio_io::AsyncEnsuredBufReader;
+pub use trailer::TrailerReader;
+pub use transaction::{Rollback, RollbackLostError, Transaction};
 
 /// Default buffer _capacity_
 ///
@@ -46,10 +87,32 @@ pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
 /// ```
 pub const DEFAULT_ENSURED_BYTES: usize = 128;
 
+// Adaptive ensured size follows an exponential moving average of consumed sizes,
+// and is updated once per `ADAPTIVE_INTERVAL` consumes.
+const ADAPTIVE_SMOOTHING_FACTOR: f64 = 8.0;
+const ADAPTIVE_INTERVAL: u32 = 16;
+
+// Count of consecutive `fill_buf()` calls without progress to panic with.
+#[cfg(debug_assertions)]
+const NO_PROGRESS_LIMIT: u32 = 1024;
+
+/// Count of retries for `Ok(0)` reads in non-strict EOF mode.
+///
+/// See [`.set_strict_eof()`](struct.EnsuredBufReader.html#method.set_strict_eof).
+pub const NON_STRICT_EOF_RETRIES: usize = 3;
+
 /// A [`BufRead`](https://doc.rust-lang.org/std/io/trait.BufRead.html)er that ensures _ensured_ bytes in buffer.
 ///
 /// `EnsuredBufReader` keeps _ensured_ bytes in buffer if it can read from underlying reader.
 /// To fetch bytes into buffer, call `fill_buf()`.
+///
+/// Only bytes read from underlying reader (or given as prefix by [`with_prefilled()`](#method.with_prefilled)) are exposed from the buffer.
+/// Initial or stale contents of the buffer are never returned, so a reused buffer given to [`from_buffer()`](#method.from_buffer) doesn't need to be cleared.
+///
+/// Like `std::io::BufReader`, reads interrupted by `ErrorKind::Interrupted` are retried, so the error is never returned.
+///
+/// Once underlying reader returns EOF, this reader is _fused_: further fills return buffered bytes only, without reading underlying reader.
+/// See [`.is_fused()`](#method.is_fused).
 pub struct EnsuredBufReader<R, B>
 where
     R: Read,
@@ -60,6 +123,38 @@ where
     pos: usize,
     cap: usize,
     ensured_size: usize,
+    treat_wouldblock_as_eof_for_fill: bool,
+    zero_read_retries: usize,
+    resize: Option<fn(&mut B, usize)>,
+    auto_grow: bool,
+    max_capacity: usize,
+    auto_shrink: Option<AutoShrink>,
+    small_fills: u32,
+    adaptive: bool,
+    adaptive_min: usize,
+    adaptive_max: usize,
+    average_consume: f64,
+    consume_count: u32,
+    effective_ensured_size: usize,
+    endianness: Endian,
+    position: u64,
+    read_from_inner: u64,
+    consumed: u64,
+    mark: Option<u64>,
+    // Checkpoints reached since `mark()`, restored by `reset_to_mark()`.
+    reached_checkpoints: Vec<u64>,
+    fill_to_capacity: bool,
+    max_read_chunk: usize,
+    read_hint: Option<fn(&R) -> Option<usize>>,
+    max_record_size: Option<usize>,
+    checkpoints: Vec<u64>,
+    eof: bool,
+    #[cfg(debug_assertions)]
+    detect_no_progress: bool,
+    #[cfg(debug_assertions)]
+    no_progress_fills: u32,
+    #[cfg(debug_assertions)]
+    last_fill: (usize, usize),
 }
 
 impl<R: Read> EnsuredBufReader<R, Vec<u8>> {
@@ -85,6 +180,54 @@ impl<R: Read> EnsuredBufReader<R, Vec<u8>> {
         )
     }
 
+    /// Creates a new `EnsuredBufReader` with a specified `capacity` and a default _ensured_ size (`DEFAULT_ENSURED_BYTES`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is smaller than `DEFAULT_ENSURED_BYTES`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let r = EnsuredBufReader::with_capacity(1024, f);
+    ///     assert_eq!(r.get_capacity(), 1024);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_capacity(capacity: usize, inner: R) -> EnsuredBufReader<R, Vec<u8>> {
+        EnsuredBufReader::with_capacity_and_ensured_size(capacity, DEFAULT_ENSURED_BYTES, inner)
+    }
+
+    /// Creates a new `EnsuredBufReader` with a default _capacity_ (`DEFAULT_BUFFER_SIZE`) and a specified `ensured_size`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ensured_size` is 0.
+    /// Panics if `ensured_size` is larger than `DEFAULT_BUFFER_SIZE`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ensured_bufreader::{DEFAULT_BUFFER_SIZE, EnsuredBufReader};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let r = EnsuredBufReader::with_ensured_size(32, f);
+    ///     assert_eq!(r.get_capacity(), DEFAULT_BUFFER_SIZE);
+    ///     assert_eq!(r.get_ensured_size(), 32);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_ensured_size(ensured_size: usize, inner: R) -> EnsuredBufReader<R, Vec<u8>> {
+        EnsuredBufReader::with_capacity_and_ensured_size(DEFAULT_BUFFER_SIZE, ensured_size, inner)
+    }
+
     /// Creates a new `EnsuredBufReader` with a specified `capacity` and `ensured_size`.
     ///
     /// `capacity` must be larger than or equal to `ensured_size`.
@@ -112,27 +255,390 @@ impl<R: Read> EnsuredBufReader<R, Vec<u8>> {
         ensured_size: usize,
         inner: R,
     ) -> EnsuredBufReader<R, Vec<u8>> {
-        assert_ne!(ensured_size, 0, "'ensure' must be positive.");
-        assert!(
-            capacity >= ensured_size,
-            "'capacity' ({}) must be larger than or equal to 'ensured_size' ({}).",
-            capacity,
-            ensured_size
-        );
-        EnsuredBufReader {
+        match EnsuredBufReader::try_with_capacity_and_ensured_size(capacity, ensured_size, inner) {
+            Ok(r) => r,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Creates a new `EnsuredBufReader` with a specified `capacity` and `ensured_size`, without panicking.
+    ///
+    /// This is same as [`with_capacity_and_ensured_size()`](#method.with_capacity_and_ensured_size),
+    /// but invalid sizes are returned as error, e.g. when they come from untrusted config.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::ZeroEnsuredSize` if `ensured_size` is 0.
+    /// Returns `ConfigError::CapacityTooSmall` if `capacity` is smaller than `ensured_size`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ensured_bufreader::{ConfigError, EnsuredBufReader};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let err = EnsuredBufReader::try_with_capacity_and_ensured_size(16, 32, f).unwrap_err();
+    ///     assert_eq!(
+    ///         err,
+    ///         ConfigError::CapacityTooSmall {
+    ///             capacity: 16,
+    ///             ensured_size: 32
+    ///         }
+    ///     );
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn try_with_capacity_and_ensured_size(
+        capacity: usize,
+        ensured_size: usize,
+        inner: R,
+    ) -> Result<EnsuredBufReader<R, Vec<u8>>, ConfigError> {
+        check_sizes(capacity, ensured_size)?;
+        Ok(EnsuredBufReader::from_raw(
             inner,
-            buf: vec![0; capacity],
-            pos: 0,
-            cap: 0,
+            vec![0; capacity],
+            ensured_size,
+        ))
+    }
+
+    /// Creates a new `EnsuredBufReader` with a specified `capacity` and non-zero `ensured_size`.
+    ///
+    /// This is same as [`with_capacity_and_ensured_size()`](#method.with_capacity_and_ensured_size),
+    /// but `ensured_size` is guaranteed to be positive by its type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is smaller than `ensured_size`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use std::num::NonZeroUsize;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let ensured_size = NonZeroUsize::new(32).unwrap();
+    ///     let r = EnsuredBufReader::with_capacity_and_nonzero_ensured_size(1024, ensured_size, f);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_capacity_and_nonzero_ensured_size(
+        capacity: usize,
+        ensured_size: NonZeroUsize,
+        inner: R,
+    ) -> EnsuredBufReader<R, Vec<u8>> {
+        EnsuredBufReader::with_capacity_and_ensured_size(capacity, ensured_size.get(), inner)
+    }
+
+    /// Creates a new `EnsuredBufReader` that keeps at least `min_records` records of `record_size` bytes.
+    ///
+    /// _Ensured_ size is `record_size * min_records`, and _capacity_ is twice of it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::ZeroEnsuredSize` if `record_size` or `min_records` is 0.
+    /// Returns `ConfigError::SizeOverflow` if _capacity_ overflows `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let input: &[u8] = &[0; 64];
+    ///     let r = EnsuredBufReader::for_records(12, 4, input)?;
+    ///     assert_eq!(r.get_ensured_size(), 48);
+    ///     assert_eq!(r.get_capacity(), 96);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn for_records(
+        record_size: usize,
+        min_records: usize,
+        inner: R,
+    ) -> Result<EnsuredBufReader<R, Vec<u8>>, ConfigError> {
+        let ensured_size = record_size
+            .checked_mul(min_records)
+            .ok_or(ConfigError::SizeOverflow)?;
+        let capacity = ensured_size
+            .checked_mul(2)
+            .ok_or(ConfigError::SizeOverflow)?;
+        check_sizes(capacity, ensured_size)?;
+        Ok(EnsuredBufReader::from_raw(
+            inner,
+            vec![0; capacity],
             ensured_size,
+        ))
+    }
+
+    /// Creates a new `EnsuredBufReader` whose buffer initially contains `prefix`.
+    ///
+    /// `prefix` is delivered before bytes read from `inner`.
+    /// This is useful to "un-read" bytes that are already read from `inner` by other means.
+    /// _Capacity_ is the largest of [`DEFAULT_BUFFER_SIZE`](constant.DEFAULT_BUFFER_SIZE.html), `prefix.len()` and `ensured_size`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `ensured_size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let inner: &[u8] = b"world";
+    ///     let mut r = EnsuredBufReader::with_prefilled(b"hello, ", 16, inner)?;
+    ///
+    ///     let mut s = String::new();
+    ///     r.read_to_string(&mut s)?;
+    ///     assert_eq!(s, "hello, world");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_prefilled(
+        prefix: &[u8],
+        ensured_size: usize,
+        inner: R,
+    ) -> io::Result<EnsuredBufReader<R, Vec<u8>>> {
+        if ensured_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "'ensure' must be positive.",
+            ));
+        }
+        let capacity = cmp::max(DEFAULT_BUFFER_SIZE, cmp::max(prefix.len(), ensured_size));
+
+        // Only the rest after the prefix is zero-filled.
+        let mut buf = Vec::with_capacity(capacity);
+        buf.extend_from_slice(prefix);
+        resize_vec(&mut buf, capacity);
+        let mut r = EnsuredBufReader::from_raw(inner, buf, ensured_size);
+        r.cap = prefix.len();
+        Ok(r)
+    }
+
+    /// Sets whether the buffer grows automatically when requested size is larger than _capacity_.
+    ///
+    /// If enabled, [`.fill_buf_to_expected_size()`](#method.fill_buf_to_expected_size) grows the buffer to `expected_size`
+    /// instead of returning error, as long as `expected_size` doesn't exceed max _capacity_ (see [`.set_max_capacity()`](#method.set_max_capacity)).
+    /// Default is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(512, 1, f);
+    ///     r.set_auto_grow(true);
+    ///
+    ///     let read_bytes = r.fill_buf_to_expected_size(1024)?;
+    ///     assert!(read_bytes.len() >= 1024);
+    ///     assert_eq!(r.get_capacity(), 1024);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_auto_grow(&mut self, enabled: bool) {
+        self.resize = Some(resize_vec);
+        self.auto_grow = enabled;
+    }
+
+    /// Grows _capacity_ to at least `new_capacity`, keeping buffered bytes.
+    ///
+    /// Does nothing if _capacity_ is already `new_capacity` or larger.
+    /// This is not limited by [`.set_max_capacity()`](#method.set_max_capacity), which limits only auto growing.
+    /// Note that the buffer may shrink again by [`.set_auto_shrink()`](#method.set_auto_shrink).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0u8; 4096];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(512, 16, input);
+    ///
+    ///     r.reserve_capacity(2048);
+    ///     assert_eq!(r.fill_buf_to_expected_size(2048)?.len(), 2048);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn reserve_capacity(&mut self, new_capacity: usize) {
+        if new_capacity <= self.get_capacity() {
+            return;
+        }
+        self.move_buf_to_head();
+        resize_vec(&mut self.buf, new_capacity);
+    }
+
+    /// Fills the buffer to `expected_size` bytes or EOF, growing _capacity_ if it is smaller than `expected_size`.
+    ///
+    /// This is [`.fill_buf_to_expected_size()`](#method.fill_buf_to_expected_size) after [`.reserve_capacity()`](#method.reserve_capacity),
+    /// so growth is not limited by [`.set_max_capacity()`](#method.set_max_capacity).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0u8; 8192];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(512, 16, input);
+    ///
+    ///     assert_eq!(r.fill_buf_growing(4096)?.len(), 4096);
+    ///     assert_eq!(r.get_capacity(), 4096);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fill_buf_growing(&mut self, expected_size: usize) -> io::Result<&[u8]> {
+        self.reserve_capacity(expected_size);
+        self.fill_buf_to_expected_size(expected_size)
+    }
+
+    /// Sets max _capacity_ that auto growing can reach.
+    ///
+    /// Requests larger than this fail with `ErrorKind::InvalidInput` as usual.
+    /// Default is unbounded.
+    /// This doesn't shrink current buffer.
+    pub fn set_max_capacity(&mut self, max_capacity: usize) {
+        self.max_capacity = max_capacity;
+    }
+
+    /// Sets automatic shrinking of the buffer.
+    ///
+    /// If `Some`, after `after_small_fills` consecutive fills that leave fewer than `baseline` bytes in the buffer,
+    /// the buffer is compacted and truncated to `baseline`.
+    /// If `None`, the buffer never shrinks automatically. Default is `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `baseline` is smaller than _ensured_ size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::{AutoShrink, EnsuredBufReader};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0u8; 4096];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4096, 16, input);
+    ///     r.set_auto_shrink(Some(AutoShrink {
+    ///         baseline: 1024,
+    ///         after_small_fills: 2,
+    ///     }));
+    ///
+    ///     // Reads whole input, so this fill is not small.
+    ///     r.fill_buf()?;
+    ///     r.consume(4096);
+    ///
+    ///     r.fill_buf()?;
+    ///     r.fill_buf()?;
+    ///     assert_eq!(r.get_capacity(), 1024);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_auto_shrink(&mut self, auto_shrink: Option<AutoShrink>) {
+        if let Some(ref s) = auto_shrink {
+            assert!(
+                s.baseline >= self.ensured_size,
+                "'baseline' ({}) must be larger than or equal to 'ensured_size' ({}).",
+                s.baseline,
+                self.ensured_size
+            );
         }
+        self.resize = Some(resize_vec);
+        self.auto_shrink = auto_shrink;
+        self.small_fills = 0;
+    }
+
+    /// Fills the buffer to `prefix_len` bytes, and returns a copy of them with the reader.
+    ///
+    /// The prefix is not consumed, so the returned reader still reads from the current position.
+    /// This is useful to inspect a header such as magic number, then pass the reader to other code.
+    /// Returned prefix is shorter than `prefix_len` only if EOF is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `prefix_len` is larger than _capacity_.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"\x89PNG...";
+    ///     let (magic, mut r) = EnsuredBufReader::new(input).into_peekable(4)?;
+    ///     assert_eq!(magic, b"\x89PNG");
+    ///
+    ///     let mut all = Vec::new();
+    ///     r.read_to_end(&mut all)?;
+    ///     assert_eq!(all, b"\x89PNG...");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn into_peekable(mut self, prefix_len: usize) -> io::Result<(Vec<u8>, Self)> {
+        let prefix = self.peek_up_to(prefix_len)?.to_vec();
+        Ok((prefix, self))
+    }
+}
+
+/// Settings of automatic buffer shrinking. See [`.set_auto_shrink()`](struct.EnsuredBufReader.html#method.set_auto_shrink).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoShrink {
+    /// _Capacity_ the buffer shrinks to.
+    ///
+    /// This is never smaller than _ensured_ size: it is rejected by `.set_auto_shrink()`,
+    /// and raised by `.set_ensured_size()`, so shrinking never breaks the guarantee of _ensured_ size.
+    pub baseline: usize,
+    /// Count of consecutive small fills required to shrink.
+    pub after_small_fills: u32,
+}
+
+// Buffer handling below is shared with `AsyncEnsuredBufReader`, so both fill in same way.
+
+// Moves buffered bytes `buf[*pos..*cap]` to head of `buf`.
+fn move_to_head(buf: &mut [u8], pos: &mut usize, cap: &mut usize) {
+    if *pos != *cap {
+        buf.copy_within(*pos..*cap, 0);
+    }
+    *cap -= *pos;
+    *pos = 0;
+}
+
+// Moves buffered bytes to head of `buf`, if free space from `pos` is smaller than `expected_size`.
+fn make_room(buf: &mut [u8], pos: &mut usize, cap: &mut usize, expected_size: usize) {
+    if buf.len() - *pos < expected_size {
+        move_to_head(buf, pos, cap);
     }
 }
 
+// Returns whether reading is retried on the error while filling, instead of returning it.
+fn is_retried(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::Interrupted
+}
+
+// Grown bytes are filled with 0 only to initialize them. They are overwritten by reads before exposed.
+// Skipping it needs `set_len()` over uninitialized bytes, which is not sound to pass to `Read::read()`.
+fn resize_vec(buf: &mut Vec<u8>, len: usize) {
+    buf.resize(len, 0);
+}
+
 impl<R: Read> EnsuredBufReader<R, &mut [u8]> {
     /// Creates a new `EnsuredBufReader` with given buffer.
     ///
     /// Buffer length must be larger than or equal to [`DEFAULT_ENSURED_BYTES`](constant.DEFAULT_ENSURED_BYTES.html).
+    /// Contents of the buffer are not cleared, and never exposed.
     ///
     /// # Panics
     ///
@@ -196,13 +702,7 @@ impl<R: Read> EnsuredBufReader<R, &mut [u8]> {
             buf.len(),
             ensured_size
         );
-        EnsuredBufReader {
-            inner,
-            buf,
-            pos: 0,
-            cap: 0,
-            ensured_size,
-        }
+        EnsuredBufReader::from_raw(inner, buf, ensured_size)
     }
 }
 
@@ -210,6 +710,7 @@ impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
     /// Creates a new `EnsuredBufReader` with given buffer.
     ///
     /// Buffer length must be larger than or equal to [`DEFAULT_ENSURED_BYTES`](constant.DEFAULT_ENSURED_BYTES.html).
+    /// Contents of the buffer are not cleared, and never exposed, so a buffer from a pool can be reused as is.
     ///
     /// # Panics
     ///
@@ -245,184 +746,1588 @@ impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
             buf.as_ref().len(),
             ensured_size
         );
-        EnsuredBufReader {
-            inner,
-            buf,
-            pos: 0,
-            cap: 0,
-            ensured_size,
-        }
+        EnsuredBufReader::from_raw(inner, buf, ensured_size)
     }
 
-    /// Returns a reference to current buffer.
-    /// This method doesn't read bytes from underlying reader.
+    /// Creates a new `EnsuredBufReader` with given buffer and a specified non-zero `ensured_size`.
     ///
-    /// # Examples
+    /// This is same as [`from_buffer_and_ensured_size()`](#method.from_buffer_and_ensured_size),
+    /// but `ensured_size` is guaranteed to be positive by its type.
     ///
-    /// ```
-    /// use std::fs::File;
-    /// use std::io::{self, BufRead};
-    /// use ensured_bufreader::EnsuredBufReader;
+    /// # Panics
     ///
-    /// fn main() -> io::Result<()> {
+    /// Panics if buffer is smaller than `ensured_size`.
+    pub fn from_buffer_and_nonzero_ensured_size(
+        buf: B,
+        ensured_size: NonZeroUsize,
+        inner: R,
+    ) -> EnsuredBufReader<R, B> {
+        EnsuredBufReader::from_buffer_and_ensured_size(buf, ensured_size.get(), inner)
+    }
+
+    /// Creates a new `EnsuredBufReader` from parts returned by [`.into_parts()`](#method.into_parts).
+    ///
+    /// `buf[pos..cap]` are buffered bytes, which are delivered before bytes from `inner`.
+    /// Position and other settings are not restored, so [`.position()`](#method.position) starts from 0.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::InvalidBufferedRange` unless `pos <= cap <= buf.len()`.
+    /// Returns `ConfigError::ZeroEnsuredSize` if `ensured_size` is 0.
+    /// Returns `ConfigError::CapacityTooSmall` if buffer is smaller than `ensured_size`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 2, input);
+    ///     r.fill_buf()?;
+    ///     r.consume(1);
+    ///
+    ///     let (inner, buf, pos, cap, ensured_size) = r.into_parts();
+    ///     let mut r = EnsuredBufReader::from_parts(inner, buf, pos, cap, ensured_size).unwrap();
+    ///     assert_eq!(r.fill_buf()?, b"bcd");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_parts(
+        inner: R,
+        buf: B,
+        pos: usize,
+        cap: usize,
+        ensured_size: usize,
+    ) -> Result<EnsuredBufReader<R, B>, ConfigError> {
+        let capacity = buf.as_ref().len();
+        if pos > cap || cap > capacity {
+            return Err(ConfigError::InvalidBufferedRange { pos, cap, capacity });
+        }
+        check_sizes(capacity, ensured_size)?;
+        let mut r = EnsuredBufReader::from_raw(inner, buf, ensured_size);
+        r.pos = pos;
+        r.cap = cap;
+        Ok(r)
+    }
+
+    /// Returns a reference to current buffer.
+    /// This method doesn't read bytes from underlying reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use std::io::{self, BufRead};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> io::Result<()> {
     ///     let f = File::open("README.md")?;
     ///     let mut r = EnsuredBufReader::new(f);
     ///
-    ///     // Read bytes from file and consume 8 bytes.
-    ///     let read_bytes = r.fill_buf()?.to_owned();
-    ///     r.consume(8);
-    ///     
-    ///     // Get buffer.
-    ///     // Current buffer should be　8 bytes shorter than `read_bytes`.
-    ///     let buf = r.buffer();
-    ///     assert_eq!(buf, &read_bytes[8..]);
+    ///     // Read bytes from file and consume 8 bytes.
+    ///     let read_bytes = r.fill_buf()?.to_owned();
+    ///     r.consume(8);
+    ///     
+    ///     // Get buffer.
+    ///     // Current buffer should be　8 bytes shorter than `read_bytes`.
+    ///     let buf = r.buffer();
+    ///     assert_eq!(buf, &read_bytes[8..]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf.as_ref()[self.pos..self.cap]
+    }
+
+    /// Fills the buffer like `fill_buf()`, and returns mutable reference to buffered bytes.
+    ///
+    /// This is useful for in-place transform before consuming, such as decryption of stream cipher.
+    ///
+    /// **Note**: Caller is responsible for correctness of mutated bytes.
+    /// Bytes not consumed are kept as mutated, and returned again by next fill with newly read bytes after them.
+    /// A fill may also move them within the buffer, so track how many leading bytes are already transformed, not their addresses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0x21, 0x2c, 0x25, 0x25, 0x26];
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let buf = r.fill_buf_mut()?;
+    ///     for b in buf.iter_mut() {
+    ///         *b ^= 0x49;
+    ///     }
+    ///     assert_eq!(r.buffer(), b"hello");
+    ///     r.consume(5);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fill_buf_mut(&mut self) -> io::Result<&mut [u8]> {
+        self.fill_buf()?;
+        let (pos, cap) = (self.pos, self.cap);
+        Ok(&mut self.buf.as_mut()[pos..cap])
+    }
+
+    /// Fills the buffer like `fill_buf()`, and returns it with the stream offset of its first byte.
+    ///
+    /// The offset is [`.position()`](#method.position), so offset of `buffer()[i]` is `offset + i`.
+    /// This is useful to compute spans of tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo bar";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     r.fill_buf()?;
+    ///     r.consume(4);
+    ///     let (buf, offset) = r.fill_buf_with_offset()?;
+    ///     assert_eq!(buf, b"bar");
+    ///     assert_eq!(offset, 4);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fill_buf_with_offset(&mut self) -> io::Result<(&[u8], u64)> {
+        let offset = self.position;
+        Ok((self.fill_buf()?, offset))
+    }
+
+    /// Try to fill buffer and return reference to buffer.
+    /// The buffer filled at least `expected_size` bytes if `EnsuredBufReader` could read from underlying reader.
+    ///
+    /// Returned bytes are fewer than `expected_size` only if EOF is reached,
+    /// or `WouldBlock` is returned with [`.set_treat_wouldblock_as_eof_for_fill()`](#method.set_treat_wouldblock_as_eof_for_fill).
+    /// Returned bytes are never more than _capacity_.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `expected_size` is larger than _capacity_.
+    /// If auto growing is enabled by [`.set_auto_grow()`](#method.set_auto_grow), the buffer grows instead
+    /// unless `expected_size` is larger than max _capacity_.
+    ///
+    /// # Examples
+    ///
+    /// The buffer will be filled to `expected_size`.
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use std::io::{self, BufRead};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 1, f);
+    ///
+    ///     // Fill buffer.
+    ///     let read_bytes = r.fill_buf_to_expected_size(512)?;
+    ///     assert!(read_bytes.len() >= 512);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// If `expected_size` is larger than _capacity_, error will be returned.
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use std::io::{self, BufRead, ErrorKind};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(512, 1, f);
+    ///
+    ///     let err = r.fill_buf_to_expected_size(513).unwrap_err();
+    ///     assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fill_buf_to_expected_size(&mut self, expected_size: usize) -> io::Result<&[u8]> {
+        let stop_at_would_block = self.treat_wouldblock_as_eof_for_fill;
+        self.fill_to(expected_size, stop_at_would_block)?;
+        Ok(self.buffer())
+    }
+
+    /// Fills the buffer to at least `n` bytes, like `read_exact()` does for reading.
+    ///
+    /// Unlike [`.fill_buf_to_expected_size()`](#method.fill_buf_to_expected_size),
+    /// the returned slice is never shorter than `n`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if the stream ends before `n` bytes are buffered.
+    /// Bytes read before that are kept in the buffer.
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `n` is larger than _capacity_.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::ErrorKind;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 1, input);
+    ///
+    ///     assert_eq!(&r.fill_buf_exact(4)?[..4], b"abcd");
+    ///
+    ///     let err = r.fill_buf_exact(8).unwrap_err();
+    ///     assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fill_buf_exact(&mut self, n: usize) -> io::Result<&[u8]> {
+        let buf = self.fill_buf_to_expected_size(n)?;
+        if buf.len() < n {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stream ended before enough bytes are buffered.",
+            ));
+        }
+        Ok(buf)
+    }
+
+    /// Fills the buffer as much as possible, until it is full or EOF.
+    ///
+    /// This is [`.fill_buf_to_expected_size()`](#method.fill_buf_to_expected_size) with _capacity_, which never fails by `ErrorKind::InvalidInput`.
+    /// Buffered bytes are moved to head of the buffer if needed.
+    /// Fewer bytes than _capacity_ are returned only if EOF is reached.
+    ///
+    /// [`.set_fill_to_capacity()`](#method.set_fill_to_capacity) lets `fill_buf()` do this when bytes are fewer than _ensured_ size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0u8; 4096];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 16, input);
+    ///
+    ///     r.fill_buf()?;
+    ///     r.consume(1000);
+    ///     assert_eq!(r.fill_to_capacity()?.len(), 1024);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fill_to_capacity(&mut self) -> io::Result<&[u8]> {
+        let capacity = self.get_capacity();
+        self.fill_buf_to_expected_size(capacity)
+    }
+
+    /// Fills the buffer like `fill_buf()` without blocking on nonblocking reader.
+    ///
+    /// Returns `Ok(None)` if underlying reader returns `ErrorKind::WouldBlock` before _ensured_ bytes are buffered.
+    /// Bytes read before that are kept in the buffer, so call this again when underlying reader becomes ready.
+    /// Returns `Ok(Some(buffer))` if _ensured_ bytes are buffered, or EOF is reached.
+    ///
+    /// `WouldBlock` is handled this way regardless of [`.set_treat_wouldblock_as_eof_for_fill()`](#method.set_treat_wouldblock_as_eof_for_fill).
+    ///
+    /// # Errors
+    ///
+    /// Errors from underlying reader other than `WouldBlock` are returned as is.
+    pub fn try_fill_buf(&mut self) -> io::Result<Option<&[u8]>> {
+        let ensured_size = self.effective_ensured_size();
+        let expected_size = if self.fill_to_capacity && self.current_bytes() < ensured_size {
+            self.get_capacity()
+        } else {
+            ensured_size
+        };
+        if self.fill_to(expected_size, true)? && self.current_bytes() < ensured_size {
+            return Ok(None);
+        }
+        Ok(Some(self.buffer()))
+    }
+
+    /// Returns up to `n` bytes from the buffer without consuming them.
+    ///
+    /// The buffer is filled to `n` bytes if possible. Fewer bytes are returned only if EOF is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `n` is larger than _capacity_.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.peek_up_to(4)?, b"abcd");
+    ///     assert_eq!(r.peek_up_to(100)?, b"abcdef");
+    ///
+    ///     let mut s = String::new();
+    ///     r.read_to_string(&mut s)?;
+    ///     assert_eq!(s, "abcdef");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn peek_up_to(&mut self, n: usize) -> io::Result<&[u8]> {
+        let buf = self.fill_buf_to_expected_size(n)?;
+        Ok(&buf[..cmp::min(n, buf.len())])
+    }
+
+    /// Returns up to `n` bytes from current position without consuming.
+    ///
+    /// This is same as [`.peek_up_to()`](#method.peek_up_to).
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `n` is larger than _capacity_.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.peek(2)?, b"ab");
+    ///     let mut buf = [0u8; 2];
+    ///     r.read_exact(&mut buf)?;
+    ///     assert_eq!(&buf, b"ab");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn peek(&mut self, n: usize) -> io::Result<&[u8]> {
+        self.peek_up_to(n)
+    }
+
+    /// Copies next `buf.len()` bytes into `buf` without consuming, like `read_exact()` without advancing.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if the stream ends before `buf.len()` bytes.
+    /// In this case, contents of `buf` are unspecified.
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `buf.len()` is larger than _capacity_.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let mut header = [0u8; 4];
+    ///     r.peek_exact(&mut header)?;
+    ///     assert_eq!(&header, b"abcd");
+    ///     assert_eq!(r.position(), 0);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn peek_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let n = buf.len();
+        buf.copy_from_slice(&self.fill_buf_exact(n)?[..n]);
+        Ok(())
+    }
+
+    /// Returns the byte at `offset` from current position without consuming.
+    ///
+    /// Returns `None` if EOF is reached before `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `offset + 1` is larger than _capacity_.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abc";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.peek_byte_at(2)?, Some(b'c'));
+    ///     assert_eq!(r.peek_byte_at(3)?, None);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn peek_byte_at(&mut self, offset: usize) -> io::Result<Option<u8>> {
+        let buf = self.fill_buf_to_expected_size(offset.saturating_add(1))?;
+        Ok(buf.get(offset).cloned())
+    }
+
+    /// Calls `f` with next `n` bytes without consuming them, and returns its result.
+    ///
+    /// This is useful to inspect a header and decide whether to consume it,
+    /// without holding a borrow of the buffer across `consume()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `n` is larger than _capacity_.
+    /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if EOF is reached before `n` bytes are buffered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"\x00\x05hello";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let len = r.peek_map(2, |b| u16::from(b[0]) << 8 | u16::from(b[1]))?;
+    ///     if len == 5 {
+    ///         r.consume(2);
+    ///     }
+    ///     assert_eq!(r.buffer(), b"hello");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn peek_map<T, F: FnOnce(&[u8]) -> T>(&mut self, n: usize, f: F) -> io::Result<T> {
+        let buf = self.fill_buf_exact(n)?;
+        Ok(f(&buf[..n]))
+    }
+
+    /// Reads exact `out.len()` bytes, bypassing internal buffer if `out` is larger than _capacity_.
+    ///
+    /// If `out.len()` is larger than _capacity_, buffered bytes are copied into `out` first
+    /// and the rest is read directly from underlying reader.
+    /// Otherwise, this behaves like `read_exact()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if EOF is reached before `out` is filled.
+    /// In this case, contents of `out` are unspecified.
+    ///
+    /// Bytes read before an error are consumed, and [`.position()`](#method.position) counts them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(128, 16, f);
+    ///
+    ///     let mut out = [0u8; 1024];
+    ///     r.read_exact_bypassing(&mut out)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_exact_bypassing(&mut self, out: &mut [u8]) -> io::Result<()> {
+        if out.len() <= self.get_capacity() {
+            return self.read_exact(out);
+        }
+
+        let n = self.current_bytes();
+        out[..n].copy_from_slice(self.buffer());
+        self.consume(n);
+
+        let mut filled = n;
+        let mut zero_reads = 0;
+        let result = loop {
+            if filled == out.len() {
+                break Ok(());
+            }
+            if self.eof {
+                break Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "stream ended before enough bytes are read.",
+                ));
+            }
+            let end = filled + cmp::min(out.len() - filled, self.max_read_chunk);
+            match self.inner.read(&mut out[filled..end]) {
+                Ok(0) if zero_reads < self.zero_read_retries => zero_reads += 1,
+                Ok(0) => self.eof = true,
+                Ok(len) => {
+                    filled += len;
+                    zero_reads = 0;
+                }
+                Err(ref e) if is_retried(e) => {}
+                Err(e) => break Err(e),
+            }
+        };
+
+        // Bytes read directly are consumed even if failed, because they can't be put back.
+        let bypassed = (filled - n) as u64;
+        self.read_from_inner += bypassed;
+        self.advance_position(bypassed);
+        self.consumed += bypassed;
+        result
+    }
+
+    /// Reads all bytes until EOF into `buf`, failing if they are more than `max` bytes.
+    ///
+    /// This is bounded version of `read_to_end()`, for untrusted input.
+    /// Returns count of appended bytes.
+    /// `buf` grows as bytes are read, so `max` is not reserved up front.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidData` and wraps [`RecordTooLongError`](struct.RecordTooLongError.html),
+    /// as soon as the stream turns out to be longer than `max`.
+    /// In this case, first `max` bytes are appended to `buf` and consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::ErrorKind;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"0123456789";
+    ///
+    ///     let mut buf = Vec::new();
+    ///     assert_eq!(EnsuredBufReader::new(input).read_to_end_limited(&mut buf, 10)?, 10);
+    ///
+    ///     let mut buf = Vec::new();
+    ///     let err = EnsuredBufReader::new(input).read_to_end_limited(&mut buf, 9).unwrap_err();
+    ///     assert_eq!(err.kind(), ErrorKind::InvalidData);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_to_end_limited(&mut self, buf: &mut Vec<u8>, max: usize) -> io::Result<usize> {
+        let mut total = 0;
+        loop {
+            let (n, too_long) = {
+                let available = self.fill_buf()?;
+                if available.is_empty() {
+                    return Ok(total);
+                }
+                let n = cmp::min(available.len(), max - total);
+                buf.extend_from_slice(&available[..n]);
+                (n, n < available.len())
+            };
+            self.consume(n);
+            total += n;
+            if too_long {
+                return Err(RecordTooLongError::new(max).into_io_error());
+            }
+        }
+    }
+
+    /// Discards next `n` bytes, or until EOF.
+    ///
+    /// Buffered bytes are consumed first, then the internal buffer is filled and consumed repeatedly,
+    /// so no scratch buffer is allocated.
+    /// Returns count of skipped bytes, which is smaller than `n` only if EOF is reached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"headerbody";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.skip(6)?, 6);
+    ///     let mut s = String::new();
+    ///     r.read_to_string(&mut s)?;
+    ///     assert_eq!(s, "body");
+    ///
+    ///     assert_eq!(r.skip(6)?, 0);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn skip(&mut self, n: u64) -> io::Result<u64> {
+        let mut skipped = 0;
+        while skipped < n {
+            if self.current_bytes() == 0 && self.fill_buf()?.is_empty() {
+                break;
+            }
+            let m = cmp::min(self.current_bytes() as u64, n - skipped) as usize;
+            self.consume(m);
+            skipped += m as u64;
+        }
+        Ok(skipped)
+    }
+
+    /// Converts into a `Cursor` over buffered bytes only, dropping underlying reader.
+    ///
+    /// This allocates a new `Vec<u8>` and copies buffered bytes into it.
+    /// Bytes not yet read from underlying reader are not included, so the stream is truncated to buffered content.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Read};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdefgh";
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 4, input);
+    ///     r.fill_buf()?;
+    ///     r.consume(1);
+    ///
+    ///     let mut c = r.into_buffered_only();
+    ///     let mut s = String::new();
+    ///     c.read_to_string(&mut s)?;
+    ///     assert_eq!(s, "bcd");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn into_buffered_only(self) -> io::Cursor<Vec<u8>> {
+        io::Cursor::new(self.buffer().to_vec())
+    }
+
+    /// Splits into buffered bytes and underlying reader, to hand off rest of the stream.
+    ///
+    /// Returned reader is positioned just after the buffered bytes,
+    /// so the buffered bytes followed by bytes read from the reader make up the rest of the stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Read};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdefgh";
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 4, input);
+    ///     r.fill_buf()?;
+    ///     r.consume(1);
+    ///
+    ///     let (buffered, rest) = r.into_remaining_reader();
+    ///     assert_eq!(buffered, b"bcd");
+    ///
+    ///     let mut r = EnsuredBufReader::new(rest);
+    ///     let mut s = String::new();
+    ///     r.read_to_string(&mut s)?;
+    ///     assert_eq!(s, "efgh");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn into_remaining_reader(self) -> (Vec<u8>, R) {
+        let buffered = self.buffer().to_vec();
+        (buffered, self.inner)
+    }
+
+    /// Unwraps this `EnsuredBufReader`, returning underlying reader.
+    ///
+    /// **Note**: Buffered bytes are lost. Use [`.into_remaining_reader()`](#method.into_remaining_reader) to keep them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Read};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdefgh";
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 4, input);
+    ///     r.fill_buf()?;
+    ///
+    ///     // "abcd" in the buffer is lost.
+    ///     let mut inner = r.into_inner();
+    ///     let mut s = String::new();
+    ///     inner.read_to_string(&mut s)?;
+    ///     assert_eq!(s, "efgh");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Splits into underlying reader, buffer, range of buffered bytes and _ensured_ size.
+    ///
+    /// Returns `(inner, buf, pos, cap, ensured_size)`, where `buf[pos..cap]` are buffered bytes,
+    /// so [`from_parts()`](#method.from_parts) can resume reading later.
+    pub fn into_parts(self) -> (R, B, usize, usize, usize) {
+        (self.inner, self.buf, self.pos, self.cap, self.ensured_size)
+    }
+
+    /// Gets a reference to underlying reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Cursor};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let mut r = EnsuredBufReader::new(Cursor::new(vec![0u8; 1000]));
+    ///     r.fill_buf()?;
+    ///     assert_eq!(r.get_ref().position(), 1000);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to underlying reader.
+    ///
+    /// **Note**: Reading directly from underlying reader desynchronizes the buffer,
+    /// because bytes read by it are not known by this reader and skipped from the stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Converts backing buffer into another type, keeping buffered bytes and settings.
+    ///
+    /// `f` must return a buffer of same length with same contents, e.g. a wrapper of given buffer.
+    /// Automatic growing and shrinking are disabled because they need `Vec<u8>` buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if length of the buffer returned by `f` is different from given buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Read};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///     r.fill_buf()?;
+    ///     r.consume(2);
+    ///
+    ///     let mut r = r.map_buffer(|v| v.into_boxed_slice());
+    ///     let mut s = String::new();
+    ///     r.read_to_string(&mut s)?;
+    ///     assert_eq!(s, "cdef");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn map_buffer<B2, F>(self, f: F) -> EnsuredBufReader<R, B2>
+    where
+        B2: AsRef<[u8]> + AsMut<[u8]>,
+        F: FnOnce(B) -> B2,
+    {
+        let len = self.buf.as_ref().len();
+        let buf = f(self.buf);
+        assert_eq!(
+            buf.as_ref().len(),
+            len,
+            "buffer length must not be changed by 'f'."
+        );
+
+        EnsuredBufReader {
+            inner: self.inner,
+            buf,
+            pos: self.pos,
+            cap: self.cap,
+            ensured_size: self.ensured_size,
+            treat_wouldblock_as_eof_for_fill: self.treat_wouldblock_as_eof_for_fill,
+            zero_read_retries: self.zero_read_retries,
+            resize: None,
+            auto_grow: false,
+            max_capacity: self.max_capacity,
+            auto_shrink: None,
+            small_fills: 0,
+            adaptive: self.adaptive,
+            adaptive_min: self.adaptive_min,
+            adaptive_max: self.adaptive_max,
+            average_consume: self.average_consume,
+            consume_count: self.consume_count,
+            effective_ensured_size: self.effective_ensured_size,
+            endianness: self.endianness,
+            position: self.position,
+            read_from_inner: self.read_from_inner,
+            consumed: self.consumed,
+            mark: self.mark,
+            reached_checkpoints: self.reached_checkpoints,
+            fill_to_capacity: self.fill_to_capacity,
+            max_read_chunk: self.max_read_chunk,
+            read_hint: self.read_hint,
+            max_record_size: self.max_record_size,
+            checkpoints: self.checkpoints,
+            eof: self.eof,
+            #[cfg(debug_assertions)]
+            detect_no_progress: self.detect_no_progress,
+            #[cfg(debug_assertions)]
+            no_progress_fills: self.no_progress_fills,
+            #[cfg(debug_assertions)]
+            last_fill: self.last_fill,
+        }
+    }
+
+    /// Get current _capacity_ size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let r = EnsuredBufReader::new(f);
+    ///
+    ///     assert_eq!(r.get_capacity(), 8192);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn get_capacity(&self) -> usize {
+        self.buf.as_ref().len()
+    }
+
+    /// Get current _ensured_ size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let f = File::open("README.md")?;
+    ///     let r = EnsuredBufReader::new(f);
+    ///
+    ///     assert_eq!(r.get_ensured_size(), 128);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn get_ensured_size(&self) -> usize {
+        self.ensured_size
+    }
+
+    /// Changes _ensured_ size.
+    ///
+    /// Buffered bytes are kept, and next `fill_buf()` fills the buffer to new _ensured_ size.
+    /// If automatic shrinking is set by [`.set_auto_shrink()`](#method.set_auto_shrink) and its baseline is smaller than new _ensured_ size,
+    /// the baseline is raised to new _ensured_ size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::ZeroEnsuredSize` if `ensured_size` is 0.
+    /// Returns `ConfigError::CapacityTooSmall` if `ensured_size` is larger than _capacity_.
+    /// In these cases, the reader is not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0u8; 4096];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 16, input);
+    ///
+    ///     r.set_ensured_size(512).unwrap();
+    ///     assert!(r.fill_buf()?.len() >= 512);
+    ///     assert!(r.set_ensured_size(2048).is_err());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_ensured_size(&mut self, ensured_size: usize) -> Result<(), ConfigError> {
+        check_sizes(self.get_capacity(), ensured_size)?;
+        self.ensured_size = ensured_size;
+        if let Some(ref mut auto_shrink) = self.auto_shrink {
+            auto_shrink.baseline = cmp::max(auto_shrink.baseline, ensured_size);
+        }
+        Ok(())
+    }
+
+    /// Changes _ensured_ size like [`.set_ensured_size()`](#method.set_ensured_size), and compacts the buffer immediately.
+    ///
+    /// Buffered bytes are moved to head of the buffer, so cost of the move is paid here instead of in next fill.
+    /// This is useful to make latency of following fills predictable.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`.set_ensured_size()`](#method.set_ensured_size). If error is returned, the buffer is not compacted.
+    pub fn set_ensured_size_and_compact(&mut self, ensured_size: usize) -> Result<(), ConfigError> {
+        self.set_ensured_size(ensured_size)?;
+        self.move_buf_to_head();
+        Ok(())
+    }
+
+    /// Get _ensured_ size currently used by `fill_buf()`.
+    ///
+    /// This is same as [`.get_ensured_size()`](#method.get_ensured_size) unless adaptive _ensured_ size is enabled by [`.set_adaptive()`](#method.set_adaptive).
+    pub fn effective_ensured_size(&self) -> usize {
+        if self.adaptive {
+            cmp::min(self.effective_ensured_size, self.get_capacity())
+        } else {
+            self.ensured_size
+        }
+    }
+
+    /// Sets whether _ensured_ size used by `fill_buf()` adapts to observed `consume()` sizes.
+    ///
+    /// If enabled, running average of consumed sizes is tracked and the effective _ensured_ size is periodically
+    /// moved to it, within bounds set by [`.set_adaptive_bounds()`](#method.set_adaptive_bounds).
+    /// Adaptation starts from [`.get_ensured_size()`](#method.get_ensured_size). Default is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0u8; 4096];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 128, input);
+    ///     r.set_adaptive(true);
+    ///
+    ///     for _ in 0..256 {
+    ///         r.fill_buf()?;
+    ///         r.consume(8);
+    ///     }
+    ///     assert!(r.effective_ensured_size() < 16);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_adaptive(&mut self, enabled: bool) {
+        self.adaptive = enabled;
+        self.average_consume = self.ensured_size as f64;
+        self.consume_count = 0;
+        self.effective_ensured_size = self.ensured_size;
+    }
+
+    /// Sets bounds of adaptive _ensured_ size.
+    ///
+    /// Default bounds are `1` and _capacity_. `max` larger than _capacity_ is treated as _capacity_.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is 0 or `min` is larger than `max`.
+    pub fn set_adaptive_bounds(&mut self, min: usize, max: usize) {
+        assert_ne!(min, 0, "'min' must be positive.");
+        assert!(
+            min <= max,
+            "'min' ({}) must be smaller than or equal to 'max' ({}).",
+            min,
+            max
+        );
+        self.adaptive_min = min;
+        self.adaptive_max = max;
+        self.effective_ensured_size = self.clamp_adaptive(self.effective_ensured_size);
+    }
+
+    /// Returns logical position in the stream, i.e. position of next byte counted from where this reader was created.
+    ///
+    /// This advances by consuming, and moves back by [`.reset_to_mark()`](#method.reset_to_mark).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let mut buf = [0u8; 4];
+    ///     r.read_exact(&mut buf)?;
+    ///     assert_eq!(r.position(), 4);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns count of bytes read from underlying reader so far.
+    ///
+    /// This counts bytes pulled by fills and [`.read_exact_bypassing()`](#method.read_exact_bypassing),
+    /// so it is ahead of [`.position()`](#method.position) by buffered bytes, and never moves back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0u8; 1000];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(128, 16, input);
+    ///
+    ///     r.fill_buf()?;
+    ///     r.consume(10);
+    ///     assert_eq!(r.total_read_from_inner(), 128);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn total_read_from_inner(&self) -> u64 {
+        self.read_from_inner
+    }
+
+    /// Returns count of bytes consumed so far, including ones consumed by `read()`.
+    ///
+    /// Unlike [`.position()`](#method.position), this never moves back by [`.reset_to_mark()`](#method.reset_to_mark) or seeking.
+    /// Bytes consumed again after moving back are counted again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::new(input);
     ///
+    ///     let mut buf = [0u8; 4];
+    ///     r.read_exact(&mut buf)?;
+    ///     assert_eq!(r.total_consumed(), 4);
     ///     Ok(())
     /// }
     /// ```
-    pub fn buffer(&self) -> &[u8] {
-        &self.buf.as_ref()[self.pos..self.cap]
+    pub fn total_consumed(&self) -> u64 {
+        self.consumed
     }
 
-    /// Try to fill buffer and return reference to buffer.
-    /// The buffer filled at least `expected_size` bytes if `EnsuredBufReader` could read from underlying reader.
+    /// Marks current position to return by [`.reset_to_mark()`](#method.reset_to_mark).
     ///
-    /// # Errors
+    /// Previous mark is overwritten.
+    pub fn mark(&mut self) {
+        self.mark = Some(self.position);
+        self.reached_checkpoints.clear();
+    }
+
+    /// Returns count of bytes in buffer.
+    pub fn current_bytes(&self) -> usize {
+        self.cap - self.pos
+    }
+
+    /// Discards buffered bytes after first `n` bytes.
     ///
-    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if `expected_size` is larger than _capacity_.
+    /// This is counterpart of `consume()`, trimming from the end of the buffer.
+    /// Following reads continue from underlying reader, just after the discarded bytes.
     ///
-    /// # Examples
+    /// **Note**: Discarded bytes are lost, unless underlying reader is seeked back.
+    /// Mark set by [`.mark()`](#method.mark) is cleared, because the discarded bytes can't be reset over.
     ///
-    /// The buffer will be filled to `expected_size`.
+    /// # Panics
+    ///
+    /// Panics if `n` is larger than [`.current_bytes()`](#method.current_bytes).
+    ///
+    /// # Examples
     ///
     /// ```
-    /// use std::fs::File;
-    /// use std::io::{self, BufRead};
+    /// use std::io::BufRead;
     /// use ensured_bufreader::EnsuredBufReader;
     ///
-    /// fn main() -> io::Result<()> {
-    ///     let f = File::open("README.md")?;
-    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 1, f);
-    ///
-    ///     // Fill buffer.
-    ///     let read_bytes = r.fill_buf_to_expected_size(512)?;
-    ///     assert!(read_bytes.len() >= 512);
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::new(input);
     ///
+    ///     r.fill_buf()?;
+    ///     r.truncate_buffered(2);
+    ///     assert_eq!(r.buffer(), b"ab");
     ///     Ok(())
     /// }
     /// ```
+    pub fn truncate_buffered(&mut self, n: usize) {
+        assert!(
+            n <= self.current_bytes(),
+            "'n' ({}) must be <= count of buffered bytes ({}).",
+            n,
+            self.current_bytes()
+        );
+        self.cap = self.pos + n;
+        self.mark = None;
+    }
+
+    /// Discards all buffered bytes, keeping underlying reader and the buffer.
     ///
-    /// If `expected_size` is larger than _capacity_, error will be returned.
+    /// Following fills read from underlying reader into empty buffer, e.g. to resync after protocol error.
+    ///
+    /// **Note**: Unconsumed bytes are lost permanently. Underlying reader is not seeked back.
+    /// Mark set by [`.mark()`](#method.mark) is cleared, as [`.truncate_buffered()`](#method.truncate_buffered) does.
+    ///
+    /// # Examples
     ///
     /// ```
-    /// use std::fs::File;
-    /// use std::io::{self, BufRead, ErrorKind};
+    /// use std::io::BufRead;
     /// use ensured_bufreader::EnsuredBufReader;
     ///
-    /// fn main() -> io::Result<()> {
-    ///     let f = File::open("README.md")?;
-    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(512, 1, f);
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abcdef";
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 4, input);
     ///
-    ///     let err = r.fill_buf_to_expected_size(513).unwrap_err();
-    ///     assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    ///     r.fill_buf()?;
+    ///     r.clear();
+    ///     assert_eq!(r.buffer(), b"");
+    ///     assert_eq!(r.fill_buf()?, b"ef");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn clear(&mut self) {
+        self.pos = 0;
+        self.cap = 0;
+        self.mark = None;
+    }
+
+    /// Moves buffered bytes to head of the buffer, if free space after them is smaller than `threshold`.
+    ///
+    /// `fill_buf()` compacts the buffer only when it needs the space.
+    /// This lets the caller do it at chosen timing instead, e.g. after consuming a batch.
+    ///
+    /// # Examples
     ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0u8; 64];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, input);
+    ///
+    ///     r.fill_buf()?;
+    ///     r.consume(60);
+    ///     r.compact_if_tail_below(16);
+    ///     assert_eq!(r.buffer().len(), 4);
     ///     Ok(())
     /// }
     /// ```
-    pub fn fill_buf_to_expected_size(&mut self, expected_size: usize) -> io::Result<&[u8]> {
-        if self.current_bytes() >= expected_size {
-            return Ok(self.buffer());
+    pub fn compact_if_tail_below(&mut self, threshold: usize) {
+        if self.buf.as_mut().len() - self.cap < threshold {
+            self.move_buf_to_head();
         }
+    }
 
-        if self.buf.as_mut().len() < expected_size {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                ExpectedSizeTooLargeError(),
-            ));
-        }
-        if self.buf.as_mut().len() - self.pos < expected_size {
-            self.move_buf_to_head()
-        }
-        while self.current_bytes() < expected_size {
-            let n = self.inner.read(&mut self.buf.as_mut()[self.cap..])?;
-            if n == 0 {
-                // Reach EOF
-                break;
-            }
-            self.cap += n;
-        }
+    /// Returns count of bytes that is readable without blocking.
+    ///
+    /// This is same as [`.current_bytes()`](#method.current_bytes): bytes in buffer.
+    /// Reading more than this may block.
+    pub fn available(&self) -> usize {
+        self.current_bytes()
+    }
 
-        Ok(self.buffer())
+    /// Returns fraction of _capacity_ occupied by unconsumed bytes.
+    ///
+    /// This is `current_bytes() as f64 / get_capacity() as f64`, so the value is in `0.0..=1.0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = &[0u8; 256];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 32, input);
+    ///
+    ///     r.fill_buf()?;
+    ///     assert_eq!(r.utilization(), 0.25);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn utilization(&self) -> f64 {
+        self.current_bytes() as f64 / self.get_capacity() as f64
     }
 
-    /// Get current _capacity_ size.
+    /// Sets whether `fill_buf()` fills the buffer up to _capacity_.
+    ///
+    /// If enabled, once fewer than _ensured_ bytes are buffered, `fill_buf()` reads until the buffer is full or EOF,
+    /// instead of stopping at _ensured_ size.
+    /// This minimizes count of `read()` calls to underlying reader at cost of latency. Default is `false`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::fs::File;
+    /// use std::io::BufRead;
     /// use ensured_bufreader::EnsuredBufReader;
     ///
     /// fn main() -> std::io::Result<()> {
-    ///     let f = File::open("README.md")?;
-    ///     let r = EnsuredBufReader::new(f);
+    ///     let input: &[u8] = &[0u8; 4096];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 16, input);
+    ///     r.set_fill_to_capacity(true);
     ///
-    ///     assert_eq!(r.get_capacity(), 8192);
+    ///     r.fill_buf()?;
+    ///     r.consume(1020);
+    ///     assert_eq!(r.fill_buf()?.len(), 1024);
     ///     Ok(())
     /// }
     /// ```
-    pub fn get_capacity(&self) -> usize {
-        self.buf.as_ref().len()
+    pub fn set_fill_to_capacity(&mut self, enabled: bool) {
+        self.fill_to_capacity = enabled;
     }
 
-    /// Get current _ensured_ size.
+    /// Sets whether `fill_buf()` panics when it is called repeatedly without progress.
+    ///
+    /// If enabled, `fill_buf()` panics after many consecutive calls that return same non-empty bytes without `consume()`.
+    /// This helps to find a loop that forgets to `consume()` and spins forever.
+    /// Default is `false`.
+    ///
+    /// This works only in debug builds (with `debug_assertions`). In release builds, this does nothing and costs nothing.
+    pub fn set_detect_no_progress(&mut self, enabled: bool) {
+        #[cfg(debug_assertions)]
+        {
+            self.detect_no_progress = enabled;
+            self.no_progress_fills = 0;
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = enabled;
+    }
+
+    /// Sets maximum length of buffer passed to a single `read()` of underlying reader.
+    ///
+    /// Fill loops until _ensured_ size is reached, so this limits only size of each read, not _capacity_.
+    /// This is useful for readers such as devices that misbehave on large reads. Default is unlimited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_read_chunk` is 0.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::fs::File;
+    /// use std::io::BufRead;
     /// use ensured_bufreader::EnsuredBufReader;
     ///
     /// fn main() -> std::io::Result<()> {
-    ///     let f = File::open("README.md")?;
-    ///     let r = EnsuredBufReader::new(f);
+    ///     let input: &[u8] = &[0u8; 4096];
+    ///     let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 512, input);
+    ///     r.set_max_read_chunk(64);
     ///
-    ///     assert_eq!(r.get_ensured_size(), 128);
+    ///     assert_eq!(r.fill_buf()?.len(), 512);
     ///     Ok(())
     /// }
     /// ```
-    pub fn get_ensured_size(&self) -> usize {
-        self.ensured_size
+    pub fn set_max_read_chunk(&mut self, max_read_chunk: usize) {
+        assert!(max_read_chunk > 0, "'max_read_chunk' must be positive.");
+        self.max_read_chunk = max_read_chunk;
     }
 
-    /// Returns count of bytes in buffer.
-    pub fn current_bytes(&self) -> usize {
-        self.cap - self.pos
+    /// Sets whether `Ok(0)` from underlying reader is treated as EOF immediately.
+    ///
+    /// `Read` contract says `Ok(0)` means EOF, so default is `true`.
+    /// If `false`, up to [`NON_STRICT_EOF_RETRIES`](constant.NON_STRICT_EOF_RETRIES.html) consecutive `Ok(0)` reads are retried before concluding EOF,
+    /// to tolerate misbehaving readers that transiently return `Ok(0)`.
+    ///
+    /// Once EOF is concluded, it is remembered and later fills don't call `read()` again until seeking.
+    /// So a reader really at EOF is called `read()` only a few extra times in total, not on every fill.
+    pub fn set_strict_eof(&mut self, strict: bool) {
+        self.zero_read_retries = if strict { 0 } else { NON_STRICT_EOF_RETRIES };
     }
 
-    fn move_buf_to_head(&mut self) {
-        if self.pos == self.cap {
-            self.pos = 0;
-            self.cap = 0;
+    /// Sets whether `fill_buf()` stops filling when underlying reader returns `ErrorKind::WouldBlock`.
+    ///
+    /// If enabled, `WouldBlock` is not returned as error and bytes buffered so far are returned, even if they are fewer than _ensured_ size.
+    /// Note that an empty buffer doesn't always mean EOF in this mode.
+    /// Default is `false`.
+    ///
+    /// This is useful for nonblocking readers such as nonblocking sockets.
+    pub fn set_treat_wouldblock_as_eof_for_fill(&mut self, enabled: bool) {
+        self.treat_wouldblock_as_eof_for_fill = enabled;
+    }
+
+    fn from_raw(inner: R, buf: B, ensured_size: usize) -> EnsuredBufReader<R, B> {
+        EnsuredBufReader {
+            inner,
+            buf,
+            pos: 0,
+            cap: 0,
+            ensured_size,
+            treat_wouldblock_as_eof_for_fill: false,
+            zero_read_retries: 0,
+            resize: None,
+            auto_grow: false,
+            max_capacity: std::usize::MAX,
+            auto_shrink: None,
+            small_fills: 0,
+            adaptive: false,
+            adaptive_min: 1,
+            adaptive_max: std::usize::MAX,
+            average_consume: ensured_size as f64,
+            consume_count: 0,
+            effective_ensured_size: ensured_size,
+            endianness: Endian::Big,
+            position: 0,
+            read_from_inner: 0,
+            consumed: 0,
+            mark: None,
+            reached_checkpoints: Vec::new(),
+            fill_to_capacity: false,
+            max_read_chunk: std::usize::MAX,
+            read_hint: None,
+            max_record_size: None,
+            checkpoints: Vec::new(),
+            eof: false,
+            #[cfg(debug_assertions)]
+            detect_no_progress: false,
+            #[cfg(debug_assertions)]
+            no_progress_fills: 0,
+            #[cfg(debug_assertions)]
+            last_fill: (0, 0),
+        }
+    }
+
+    // Fills the buffer to `expected_size` bytes or EOF. Returns `true` if filling is stopped by `WouldBlock`.
+    fn fill_to(&mut self, expected_size: usize, stop_at_would_block: bool) -> io::Result<bool> {
+        if self.current_bytes() >= expected_size {
+            return Ok(false);
+        }
+        #[cfg(any(feature = "log", feature = "tracing"))]
+        let pos_before = self.pos;
+
+        if self.buf.as_mut().len() < expected_size {
+            match self.resize {
+                Some(resize) if self.auto_grow && expected_size <= self.max_capacity => {
+                    self.move_buf_to_head();
+                    resize(&mut self.buf, expected_size);
+                }
+                resize => {
+                    let max_capacity = match resize {
+                        Some(_) if self.auto_grow => Some(self.max_capacity),
+                        _ => None,
+                    };
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        ExpectedSizeTooLargeError {
+                            expected: expected_size,
+                            capacity: self.buf.as_mut().len(),
+                            max_capacity,
+                        },
+                    ));
+                }
+            }
+        }
+        make_room(
+            self.buf.as_mut(),
+            &mut self.pos,
+            &mut self.cap,
+            expected_size,
+        );
+        #[cfg(any(feature = "log", feature = "tracing"))]
+        let cap_before = self.cap;
+        let mut zero_reads = 0;
+        let mut would_block = false;
+        // Once EOF is observed, the reader is fused and never reads again.
+        while !self.eof && self.current_bytes() < expected_size {
+            let preferred = match self.read_hint {
+                Some(hint) => hint(&self.inner),
+                None => None,
+            };
+            let buf = self.buf.as_mut();
+            let mut len = cmp::min(buf.len() - self.cap, self.max_read_chunk);
+            if let Some(preferred) = preferred {
+                if preferred > 0 && len >= preferred {
+                    len -= len % preferred;
+                }
+            }
+            let end = self.cap + len;
+            match self.inner.read(&mut buf[self.cap..end]) {
+                Ok(0) if zero_reads < self.zero_read_retries => zero_reads += 1,
+                Ok(0) => {
+                    // Reach EOF
+                    self.eof = true;
+                    break;
+                }
+                Ok(n) => {
+                    zero_reads = 0;
+                    self.cap += n;
+                    self.read_from_inner += n as u64;
+                }
+                Err(ref e) if is_retried(e) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock && stop_at_would_block => {
+                    would_block = true;
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        #[cfg(any(feature = "log", feature = "tracing"))]
+        self.trace_fill(expected_size, self.cap - cap_before, self.pos != pos_before);
+        self.shrink_if_idle();
+
+        Ok(would_block)
+    }
+
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    fn trace_fill(&self, requested: usize, read: usize, shifted: bool) {
+        let buffered_after = self.current_bytes();
+        let eof = self.eof;
+        #[cfg(feature = "log")]
+        log::trace!(
+            "fill: requested={}, read={}, shifted={}, buffered_after={}, eof={}",
+            requested,
+            read,
+            shifted,
+            buffered_after,
+            eof
+        );
+        #[cfg(feature = "tracing")]
+        tracing::trace!(requested, read, shifted, buffered_after, eof, "fill");
+    }
+
+    // Counts `fill_buf()` calls returning same non-empty bytes, and panics if too many.
+    #[cfg(debug_assertions)]
+    fn check_progress(&mut self) {
+        if !self.detect_no_progress {
+            return;
+        }
+        let fill = (self.pos, self.cap);
+        if self.current_bytes() > 0 && fill == self.last_fill {
+            self.no_progress_fills += 1;
+            assert!(
+                self.no_progress_fills < NO_PROGRESS_LIMIT,
+                "fill_buf() is called {} times without consume(). Missing consume() in a loop?",
+                NO_PROGRESS_LIMIT
+            );
         } else {
-            self.buf.as_mut().copy_within(self.pos..self.cap, 0);
-            self.cap -= self.pos;
-            self.pos = 0;
+            self.no_progress_fills = 0;
+        }
+        self.last_fill = fill;
+    }
+
+    // Moves back to `position` if its byte is still in the buffer.
+    fn rewind_in_buffer(&mut self, position: u64) -> bool {
+        if position > self.position || self.position - position > self.pos as u64 {
+            return false;
+        }
+        self.pos -= (self.position - position) as usize;
+        self.position = position;
+        true
+    }
+
+    fn adapt_ensured_size(&mut self, consumed: usize) {
+        if !self.adaptive {
+            return;
+        }
+
+        self.average_consume +=
+            (consumed as f64 - self.average_consume) / ADAPTIVE_SMOOTHING_FACTOR;
+        self.consume_count += 1;
+        if self.consume_count >= ADAPTIVE_INTERVAL {
+            self.consume_count = 0;
+            self.effective_ensured_size =
+                self.clamp_adaptive(self.average_consume.round() as usize);
+        }
+    }
+
+    fn clamp_adaptive(&self, size: usize) -> usize {
+        let max = cmp::min(self.adaptive_max, self.get_capacity());
+        cmp::max(cmp::min(size, max), self.adaptive_min)
+    }
+
+    fn shrink_if_idle(&mut self) {
+        let (auto_shrink, resize) = match (self.auto_shrink, self.resize) {
+            (Some(auto_shrink), Some(resize)) => (auto_shrink, resize),
+            _ => return,
+        };
+
+        if self.current_bytes() >= auto_shrink.baseline {
+            self.small_fills = 0;
+            return;
+        }
+        self.small_fills = self.small_fills.saturating_add(1);
+
+        if self.small_fills >= auto_shrink.after_small_fills
+            && self.get_capacity() > auto_shrink.baseline
+        {
+            self.move_buf_to_head();
+            resize(&mut self.buf, auto_shrink.baseline);
+            self.small_fills = 0;
         }
     }
+
+    fn move_buf_to_head(&mut self) {
+        move_to_head(self.buf.as_mut(), &mut self.pos, &mut self.cap);
+    }
 }
 
 impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Read for EnsuredBufReader<R, B> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let n = self.fill_buf()?.read(buf)?;
+        // Filling to ensured size is needless if buffered bytes can be returned.
+        if self.current_bytes() == 0 {
+            self.fill_buf()?;
+        }
+        let n = cmp::min(buf.len(), self.current_bytes());
+        buf[..n].copy_from_slice(&self.buffer()[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+
+    // Copies buffered bytes across all of `bufs`, not only the first non-empty one as the default does.
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        if bufs.iter().all(|b| b.is_empty()) {
+            return Ok(0);
+        }
+        if self.current_bytes() == 0 {
+            self.fill_buf()?;
+        }
+        let mut n = 0;
+        for b in bufs.iter_mut() {
+            let available = &self.buf.as_ref()[self.pos + n..self.cap];
+            if available.is_empty() {
+                break;
+            }
+            let m = cmp::min(b.len(), available.len());
+            b[..m].copy_from_slice(&available[..m]);
+            n += m;
+        }
         self.consume(n);
         Ok(n)
     }
+
+    // Appends whole buffered bytes at once, instead of reading into small spare capacity of `buf` as the default does.
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let start = buf.len();
+        loop {
+            let n = {
+                let available = self.fill_buf()?;
+                if available.is_empty() {
+                    break;
+                }
+                buf.extend_from_slice(available);
+                available.len()
+            };
+            self.consume(n);
+        }
+        Ok(buf.len() - start)
+    }
+
+    // Copies into uninitialized memory directly, without zeroing it as the default does.
+    #[cfg(feature = "read_buf")]
+    fn read_buf(&mut self, mut cursor: io::BorrowedCursor<'_>) -> io::Result<()> {
+        if self.current_bytes() == 0 {
+            self.fill_buf()?;
+        }
+        let n = cmp::min(cursor.capacity(), self.current_bytes());
+        cursor.append(&self.buffer()[..n]);
+        self.consume(n);
+        Ok(())
+    }
 }
 
 impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> BufRead for EnsuredBufReader<R, B> {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        self.fill_buf_to_expected_size(self.ensured_size)
+        #[cfg(debug_assertions)]
+        self.check_progress();
+
+        let ensured_size = self.effective_ensured_size();
+        if self.fill_to_capacity && self.current_bytes() < ensured_size {
+            let capacity = self.get_capacity();
+            return self.fill_buf_to_expected_size(capacity);
+        }
+        self.fill_buf_to_expected_size(ensured_size)
+    }
+
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.read_until_limited(byte, buf)
+    }
+
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.read_line_limited(buf)
     }
 
     fn consume(&mut self, amt: usize) {
@@ -430,7 +2335,64 @@ impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> BufRead for EnsuredBufReader<R, B> {
             amt <= self.current_bytes(),
             "the amt must be <= the number of bytes in the buffer returned by fill_buf."
         );
+        self.advance_position(amt as u64);
         self.pos += amt;
+        self.consumed += amt as u64;
+        #[cfg(debug_assertions)]
+        {
+            self.no_progress_fills = 0;
+        }
+        self.adapt_ensured_size(amt);
+    }
+}
+
+/// Clones underlying reader, buffered bytes, position and configuration.
+///
+/// The clone continues independently only if cloning `R` yields independent stream position, e.g. `&[u8]`.
+impl<R, B> Clone for EnsuredBufReader<R, B>
+where
+    R: Read + Clone,
+    B: AsRef<[u8]> + AsMut<[u8]> + Clone,
+{
+    fn clone(&self) -> Self {
+        EnsuredBufReader {
+            inner: self.inner.clone(),
+            buf: self.buf.clone(),
+            pos: self.pos,
+            cap: self.cap,
+            ensured_size: self.ensured_size,
+            treat_wouldblock_as_eof_for_fill: self.treat_wouldblock_as_eof_for_fill,
+            zero_read_retries: self.zero_read_retries,
+            resize: self.resize,
+            auto_grow: self.auto_grow,
+            max_capacity: self.max_capacity,
+            auto_shrink: self.auto_shrink,
+            small_fills: self.small_fills,
+            adaptive: self.adaptive,
+            adaptive_min: self.adaptive_min,
+            adaptive_max: self.adaptive_max,
+            average_consume: self.average_consume,
+            consume_count: self.consume_count,
+            effective_ensured_size: self.effective_ensured_size,
+            endianness: self.endianness,
+            position: self.position,
+            read_from_inner: self.read_from_inner,
+            consumed: self.consumed,
+            mark: self.mark,
+            reached_checkpoints: self.reached_checkpoints.clone(),
+            fill_to_capacity: self.fill_to_capacity,
+            max_read_chunk: self.max_read_chunk,
+            read_hint: self.read_hint,
+            max_record_size: self.max_record_size,
+            checkpoints: self.checkpoints.clone(),
+            eof: self.eof,
+            #[cfg(debug_assertions)]
+            detect_no_progress: self.detect_no_progress,
+            #[cfg(debug_assertions)]
+            no_progress_fills: self.no_progress_fills,
+            #[cfg(debug_assertions)]
+            last_fill: self.last_fill,
+        }
     }
 }
 
@@ -450,14 +2412,137 @@ where
     }
 }
 
+/// An error type for invalid configuration of sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// _Ensured_ size is 0.
+    ZeroEnsuredSize,
+    /// _Capacity_ is smaller than _ensured_ size.
+    CapacityTooSmall {
+        /// Requested _capacity_.
+        capacity: usize,
+        /// Requested _ensured_ size.
+        ensured_size: usize,
+    },
+    /// Computed size overflows `usize`.
+    SizeOverflow,
+    /// Range of buffered bytes is out of the buffer.
+    InvalidBufferedRange {
+        /// Start of buffered bytes.
+        pos: usize,
+        /// End of buffered bytes.
+        cap: usize,
+        /// Length of the buffer.
+        capacity: usize,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::ZeroEnsuredSize => write!(f, "'ensure' must be positive."),
+            ConfigError::CapacityTooSmall {
+                capacity,
+                ensured_size,
+            } => write!(
+                f,
+                "'capacity' ({}) must be larger than or equal to 'ensured_size' ({}).",
+                capacity, ensured_size
+            ),
+            ConfigError::SizeOverflow => write!(f, "buffer size overflows 'usize'."),
+            ConfigError::InvalidBufferedRange { pos, cap, capacity } => write!(
+                f,
+                "buffered range ({}..{}) must be in the buffer (length {}).",
+                pos, cap, capacity
+            ),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+fn check_sizes(capacity: usize, ensured_size: usize) -> Result<(), ConfigError> {
+    if ensured_size == 0 {
+        return Err(ConfigError::ZeroEnsuredSize);
+    }
+    if capacity < ensured_size {
+        return Err(ConfigError::CapacityTooSmall {
+            capacity,
+            ensured_size,
+        });
+    }
+    Ok(())
+}
+
 /// An error type may be returned from [`.fill_buf_to_expected_size()`](struct.EnsuredBufReader.html#method.fill_buf_to_expected_size).
-#[derive(Debug, Clone, Copy)]
-pub struct ExpectedSizeTooLargeError();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpectedSizeTooLargeError {
+    expected: usize,
+    capacity: usize,
+    max_capacity: Option<usize>,
+}
+
+impl ExpectedSizeTooLargeError {
+    /// Returns requested size.
+    pub fn expected(&self) -> usize {
+        self.expected
+    }
+
+    /// Returns current _capacity_ of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns max _capacity_ set by [`.set_max_capacity()`](struct.EnsuredBufReader.html#method.set_max_capacity),
+    /// if the buffer can grow by [`.set_auto_grow()`](struct.EnsuredBufReader.html#method.set_auto_grow).
+    pub fn max_capacity(&self) -> Option<usize> {
+        self.max_capacity
+    }
+}
 
 impl fmt::Display for ExpectedSizeTooLargeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "internal buffer is too small.")
+        write!(
+            f,
+            "requested {} bytes but internal buffer capacity is {}",
+            self.expected, self.capacity
+        )?;
+        if let Some(max_capacity) = self.max_capacity {
+            write!(f, " and can grow up to {}", max_capacity)?;
+        }
+        write!(f, ".")
     }
 }
 
 impl error::Error for ExpectedSizeTooLargeError {}
+
+/// An error type returned when a record is longer than the limit, wrapped in `io::Error` with `ErrorKind::InvalidData`.
+///
+/// This is returned from such as [`.read_until_into()`](struct.EnsuredBufReader.html#method.read_until_into).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordTooLongError {
+    limit: usize,
+}
+
+impl RecordTooLongError {
+    pub(crate) fn new(limit: usize) -> RecordTooLongError {
+        RecordTooLongError { limit }
+    }
+
+    /// Returns the limit that the record exceeded.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub(crate) fn into_io_error(self) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, self)
+    }
+}
+
+impl fmt::Display for RecordTooLongError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "record is longer than limit ({} bytes).", self.limit)
+    }
+}
+
+impl error::Error for RecordTooLongError {}
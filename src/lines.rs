@@ -0,0 +1,508 @@
+use std::cmp;
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::str;
+
+use crate::delimited::find_byte;
+use crate::{EnsuredBufReader, RecordTooLongError};
+
+/// Line terminator found by [`.read_line_with_terminator()`](struct.EnsuredBufReader.html#method.read_line_with_terminator).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineTerminator {
+    /// Line ended with `\n`.
+    Lf,
+    /// Line ended with `\r\n`.
+    CrLf,
+    /// Line ended without terminator (EOF).
+    None,
+}
+
+/// Result of [`.read_line_bounded()`](struct.EnsuredBufReader.html#method.read_line_bounded).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineOutcome {
+    /// A line is read including `\n`. Holds count of read bytes.
+    Complete(usize),
+    /// EOF is reached before `\n`. Holds count of read bytes, which is 0 if already at EOF.
+    Eof(usize),
+    /// The line is longer than the limit. Rest of the line is left unconsumed.
+    TooLong {
+        /// Count of read bytes, which is equal to the limit.
+        read: usize,
+    },
+}
+
+/// An error type returned from [`.read_line_checked()`](struct.EnsuredBufReader.html#method.read_line_checked).
+#[derive(Debug)]
+pub enum Utf8LineError {
+    /// Error from reading.
+    Io(io::Error),
+    /// The line is not valid UTF-8.
+    InvalidUtf8 {
+        /// Stream offset of the first invalid byte, counted as [`.position()`](struct.EnsuredBufReader.html#method.position).
+        offset: u64,
+        /// The first invalid byte.
+        byte: u8,
+    },
+}
+
+impl fmt::Display for Utf8LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Utf8LineError::Io(ref e) => e.fmt(f),
+            Utf8LineError::InvalidUtf8 { offset, byte } => {
+                write!(f, "invalid UTF-8 byte 0x{:02x} at offset {}.", byte, offset)
+            }
+        }
+    }
+}
+
+impl error::Error for Utf8LineError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Utf8LineError::Io(ref e) => Some(e),
+            Utf8LineError::InvalidUtf8 { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for Utf8LineError {
+    fn from(e: io::Error) -> Utf8LineError {
+        Utf8LineError::Io(e)
+    }
+}
+
+impl From<Utf8LineError> for io::Error {
+    /// `Utf8LineError::InvalidUtf8` is wrapped in error that has `.kind() == ErrorKind::InvalidData`.
+    fn from(e: Utf8LineError) -> io::Error {
+        match e {
+            Utf8LineError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Reads a line and reports how it was terminated.
+    ///
+    /// Line content is appended to `buf` _without_ its terminator.
+    /// Returned `usize` is count of bytes read from stream, including the terminator.
+    /// At EOF, `(0, LineTerminator::None)` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::{EnsuredBufReader, LineTerminator};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo\r\nbar\nbaz";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let mut line = Vec::new();
+    ///     assert_eq!(r.read_line_with_terminator(&mut line)?, (5, LineTerminator::CrLf));
+    ///     assert_eq!(line, b"foo");
+    ///
+    ///     line.clear();
+    ///     assert_eq!(r.read_line_with_terminator(&mut line)?, (4, LineTerminator::Lf));
+    ///     assert_eq!(line, b"bar");
+    ///
+    ///     line.clear();
+    ///     assert_eq!(r.read_line_with_terminator(&mut line)?, (3, LineTerminator::None));
+    ///     assert_eq!(line, b"baz");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_line_with_terminator(
+        &mut self,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<(usize, LineTerminator)> {
+        let start = buf.len();
+        let n = self.read_until(b'\n', buf)?;
+
+        if buf.len() == start || buf[buf.len() - 1] != b'\n' {
+            return Ok((n, LineTerminator::None));
+        }
+        buf.pop();
+
+        if buf.len() > start && buf[buf.len() - 1] == b'\r' {
+            buf.pop();
+            Ok((n, LineTerminator::CrLf))
+        } else {
+            Ok((n, LineTerminator::Lf))
+        }
+    }
+
+    /// Streams next line to `f` in chunks, without storing the whole line.
+    ///
+    /// `f` is called zero or more times with consecutive parts of the line, the last part including `\n`.
+    /// Returns `true` if `\n` is found, or `false` if EOF is reached before.
+    /// Errors returned by `f` are returned as is; bytes passed to `f` before that are consumed.
+    ///
+    /// This is useful for lines with unbounded length, such as from untrusted input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo bar\nbaz";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let mut len = 0;
+    ///     assert!(r.process_line(|chunk| {
+    ///         len += chunk.len();
+    ///         Ok(())
+    ///     })?);
+    ///     assert_eq!(len, 8);
+    ///
+    ///     assert!(!r.process_line(|_| Ok(()))?);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn process_line<F: FnMut(&[u8]) -> io::Result<()>>(
+        &mut self,
+        mut f: F,
+    ) -> io::Result<bool> {
+        loop {
+            let (found, used) = {
+                let buf = self.fill_buf()?;
+                if buf.is_empty() {
+                    return Ok(false);
+                }
+                match find_byte(b'\n', buf) {
+                    Some(i) => {
+                        f(&buf[..=i])?;
+                        (true, i + 1)
+                    }
+                    None => {
+                        f(buf)?;
+                        (false, buf.len())
+                    }
+                }
+            };
+            self.consume(used);
+            if found {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Reads a line into `buf` like `read_line()`, but at most `max` bytes.
+    ///
+    /// `max` includes `\n`. If the line is longer than `max`, first `max` bytes are appended and `LineOutcome::TooLong` is returned.
+    /// Rest of the line is left unconsumed, so the caller can skip it and continue with next line.
+    /// In this case, a UTF-8 character cut by `max` is left unconsumed too, so `read` may be less than `max`.
+    /// Only if _capacity_ is less than 4, such character may not fit in the buffer, and it is consumed but not appended.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidData` if read bytes are not valid UTF-8.
+    /// In this case, `buf` is not changed, but the bytes are consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::{EnsuredBufReader, LineOutcome};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"short\nvery long line\nend";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///     let mut line = String::new();
+    ///
+    ///     assert_eq!(r.read_line_bounded(&mut line, 8)?, LineOutcome::Complete(6));
+    ///     assert_eq!(line, "short\n");
+    ///
+    ///     line.clear();
+    ///     assert_eq!(r.read_line_bounded(&mut line, 8)?, LineOutcome::TooLong { read: 8 });
+    ///     assert_eq!(line, "very lon");
+    ///     r.process_line(|_| Ok(()))?;
+    ///
+    ///     line.clear();
+    ///     assert_eq!(r.read_line_bounded(&mut line, 8)?, LineOutcome::Eof(3));
+    ///     assert_eq!(line, "end");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_line_bounded(&mut self, buf: &mut String, max: usize) -> io::Result<LineOutcome> {
+        let mut bytes = Vec::new();
+        // Length of an incomplete character left at head of the buffer, to be filled up.
+        let mut partial = 0;
+        let mut outcome = loop {
+            let (found, used, more) = {
+                let available = if partial > 0 {
+                    let expected = cmp::min(partial + 1, self.get_capacity());
+                    self.fill_buf_to_expected_size(expected)?
+                } else {
+                    self.fill_buf()?
+                };
+                let allowed = &available[..cmp::min(available.len(), max - bytes.len())];
+                match find_byte(b'\n', allowed) {
+                    Some(i) => (true, i + 1, false),
+                    None => (false, allowed.len(), available.len() > allowed.len()),
+                }
+            };
+            if used == 0 && !more {
+                break LineOutcome::Eof(bytes.len());
+            }
+
+            // An incomplete character is left unconsumed, so a line cut at `max` keeps it for next read.
+            let mut used = used;
+            if !found {
+                let tail = incomplete_char_len(&self.buffer()[..used]);
+                if more || tail < used {
+                    used -= tail;
+                } else if used > partial {
+                    // Whole buffer is an incomplete character, so fill more before deciding.
+                    partial = used;
+                    continue;
+                }
+            }
+            partial = 0;
+            bytes.extend_from_slice(&self.buffer()[..used]);
+            self.consume(used);
+            if found {
+                break LineOutcome::Complete(bytes.len());
+            }
+            if more {
+                break LineOutcome::TooLong { read: bytes.len() };
+            }
+        };
+
+        if let LineOutcome::TooLong { ref mut read } = outcome {
+            // The character didn't fit in the buffer, so it is already consumed.
+            let tail = incomplete_char_len(&bytes);
+            bytes.truncate(bytes.len() - tail);
+            *read = bytes.len();
+        }
+        let s =
+            String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        buf.push_str(&s);
+        Ok(outcome)
+    }
+
+    /// Reads a batch of lines, until `max_lines` lines or `max_bytes` bytes are read, or EOF is reached.
+    ///
+    /// Each line includes its `\n`, except the last line of the stream if it doesn't end with `\n`.
+    /// `max_bytes` is checked after each line, so the batch may exceed it by the last line.
+    /// An empty batch is returned at EOF.
+    ///
+    /// This is useful to hand off lines in batches, such as in log shippers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_lines` or `max_bytes` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"a\nb\nc\nd";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.next_line_batch(3, 1024)?, vec![b"a\n".to_vec(), b"b\n".to_vec(), b"c\n".to_vec()]);
+    ///     assert_eq!(r.next_line_batch(3, 1024)?, vec![b"d".to_vec()]);
+    ///     assert!(r.next_line_batch(3, 1024)?.is_empty());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn next_line_batch(
+        &mut self,
+        max_lines: usize,
+        max_bytes: usize,
+    ) -> io::Result<Vec<Vec<u8>>> {
+        assert!(max_lines > 0, "'max_lines' must be positive.");
+        assert!(max_bytes > 0, "'max_bytes' must be positive.");
+
+        let mut batch = Vec::new();
+        let mut bytes = 0;
+        while batch.len() < max_lines && bytes < max_bytes {
+            let mut line = Vec::new();
+            if self.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            bytes += line.len();
+            batch.push(line);
+        }
+        Ok(batch)
+    }
+
+    /// Reads a line into `buf` like `read_line()`, but reports where invalid UTF-8 is.
+    ///
+    /// Returns count of read bytes including `\n`, or `None` at EOF.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Utf8LineError::InvalidUtf8` if the line is not valid UTF-8.
+    /// In this case, `buf` is not changed, but the line is consumed, so reading can continue from next line.
+    /// Errors from reading are returned as `Utf8LineError::Io`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::{EnsuredBufReader, Utf8LineError};
+    ///
+    /// fn main() -> Result<(), Utf8LineError> {
+    ///     let input: &[u8] = b"ok\nbad \xff\n";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///     let mut line = String::new();
+    ///
+    ///     assert_eq!(r.read_line_checked(&mut line)?, Some(3));
+    ///     match r.read_line_checked(&mut line) {
+    ///         Err(Utf8LineError::InvalidUtf8 { offset, byte }) => {
+    ///             assert_eq!(offset, 7);
+    ///             assert_eq!(byte, 0xff);
+    ///         }
+    ///         _ => unreachable!(),
+    ///     }
+    ///     assert_eq!(r.read_line_checked(&mut line)?, None);
+    ///     assert_eq!(line, "ok\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_line_checked(&mut self, buf: &mut String) -> Result<Option<usize>, Utf8LineError> {
+        let start = self.position;
+        let mut bytes = Vec::new();
+        let n = self.read_until_limited(b'\n', &mut bytes)?;
+        if n == 0 {
+            return Ok(None);
+        }
+        match str::from_utf8(&bytes) {
+            Ok(s) => {
+                buf.push_str(s);
+                Ok(Some(n))
+            }
+            Err(e) => {
+                let i = e.valid_up_to();
+                Err(Utf8LineError::InvalidUtf8 {
+                    offset: start + i as u64,
+                    byte: bytes[i],
+                })
+            }
+        }
+    }
+
+    /// Reads a line including `\n`, replacing invalid UTF-8 with `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// Returns `None` at EOF.
+    /// This is useful for text that occasionally contains garbage bytes, such as log files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"ok\nbad \xff\n";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.read_line_lossy()?, Some("ok\n".to_string()));
+    ///     assert_eq!(r.read_line_lossy()?, Some("bad \u{fffd}\n".to_string()));
+    ///     assert_eq!(r.read_line_lossy()?, None);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_line_lossy(&mut self) -> io::Result<Option<String>> {
+        let mut bytes = Vec::new();
+        if self.read_until_limited(b'\n', &mut bytes)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    /// Returns an iterator over lines as bytes, each including `\n`.
+    ///
+    /// The last line is yielded without `\n` if the stream doesn't end with `\n`.
+    /// Unlike `lines()`, lines are not required to be UTF-8 and terminators are kept.
+    /// The iterator borrows this reader mutably while it is alive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo\n\xffbar";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let lines = r.byte_lines().collect::<std::io::Result<Vec<_>>>()?;
+    ///     assert_eq!(lines, vec![b"foo\n".to_vec(), b"\xffbar".to_vec()]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn byte_lines(&mut self) -> ByteLines<'_, R, B> {
+        ByteLines { reader: self }
+    }
+
+    pub(crate) fn read_line_limited(&mut self, buf: &mut String) -> io::Result<usize> {
+        let mut bytes = Vec::new();
+        let ret = self.read_until_limited(b'\n', &mut bytes);
+        match str::from_utf8(&bytes) {
+            Ok(s) => {
+                buf.push_str(s);
+                ret
+            }
+            // The record is cut by the limit in the middle of a character, which is consumed but not appended.
+            Err(ref e) if e.error_len().is_none() && is_record_too_long(&ret) => {
+                buf.push_str(str::from_utf8(&bytes[..e.valid_up_to()]).unwrap());
+                ret
+            }
+            // Error of reading takes precedence, as `std` does.
+            Err(e) => ret.and_then(|_| Err(io::Error::new(io::ErrorKind::InvalidData, e))),
+        }
+    }
+}
+
+/// An iterator over lines as bytes.
+///
+/// This struct is created by [`.byte_lines()`](struct.EnsuredBufReader.html#method.byte_lines).
+pub struct ByteLines<'a, R, B>
+where
+    R: Read,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    reader: &'a mut EnsuredBufReader<R, B>,
+}
+
+impl<'a, R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Iterator for ByteLines<'a, R, B> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<io::Result<Vec<u8>>> {
+        let mut line = Vec::new();
+        match self.reader.read_until_limited(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(line)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+// Returns whether `ret` is error by the record limit.
+fn is_record_too_long(ret: &io::Result<usize>) -> bool {
+    match ret {
+        Err(e) => e
+            .get_ref()
+            .map_or(false, |inner| inner.is::<RecordTooLongError>()),
+        Ok(_) => false,
+    }
+}
+
+// Returns length of an incomplete UTF-8 character at the end of `bytes`, or 0 if it ends at a character boundary.
+fn incomplete_char_len(bytes: &[u8]) -> usize {
+    for i in 1..=cmp::min(3, bytes.len()) {
+        let b = bytes[bytes.len() - i];
+        if b & 0xc0 == 0x80 {
+            continue;
+        }
+        let len = match b {
+            0xf0..=0xff => 4,
+            0xe0..=0xef => 3,
+            0xc0..=0xdf => 2,
+            _ => 1,
+        };
+        return if len > i { i } else { 0 };
+    }
+    0
+}
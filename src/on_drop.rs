@@ -0,0 +1,190 @@
+use std::io::{self, BufRead, Read};
+use std::ptr;
+use std::sync::atomic::{self, Ordering};
+
+use crate::EnsuredBufReader;
+
+/// Type of hook set by [`DropGuard::set_on_drop()`](struct.DropGuard.html#method.set_on_drop).
+///
+/// The hook runs once on the thread dropping the guard, so it may capture `Rc` or `Cell`.
+/// Because of this, `DropGuard` is not `Send`.
+pub type DropHook = Box<dyn FnOnce(&FinalStats)>;
+
+/// State of `EnsuredBufReader` when it is dropped.
+///
+/// This struct is passed to the hook set by [`DropGuard::set_on_drop()`](struct.DropGuard.html#method.set_on_drop).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FinalStats {
+    position: u64,
+    consumed: u64,
+    buffered_bytes: usize,
+    capacity: usize,
+}
+
+impl FinalStats {
+    /// Returns logical position in the stream, as [`.position()`](struct.EnsuredBufReader.html#method.position).
+    ///
+    /// This is an offset, which moves back by seeking or [`.reset_to_mark()`](struct.EnsuredBufReader.html#method.reset_to_mark).
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns total count of consumed bytes, as [`.total_consumed()`](struct.EnsuredBufReader.html#method.total_consumed).
+    pub fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Returns count of bytes left in buffer, which were never consumed.
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered_bytes
+    }
+
+    /// Returns _capacity_ of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Converts into a [`DropGuard`](struct.DropGuard.html), which runs cleanup when it is dropped.
+    ///
+    /// `EnsuredBufReader` itself doesn't implement `Drop`, so a buffer borrowed by it may be dropped before it.
+    /// The guard opts in to `Drop`, so such a buffer must outlive the guard.
+    pub fn into_drop_guard(self) -> DropGuard<R, B> {
+        DropGuard {
+            reader: Some(self),
+            on_drop: None,
+            zero_on_drop: false,
+        }
+    }
+}
+
+/// A reader that runs a hook and/or zeroes the buffer when it is dropped.
+///
+/// This struct is created by [`.into_drop_guard()`](struct.EnsuredBufReader.html#method.into_drop_guard).
+/// Use [`.get_mut()`](#method.get_mut) to call methods of underlying `EnsuredBufReader`.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+/// use ensured_bufreader::EnsuredBufReader;
+///
+/// fn main() -> std::io::Result<()> {
+///     let input: &[u8] = b"0123456789";
+///     let consumed = Arc::new(AtomicU64::new(0));
+///
+///     let mut r = EnsuredBufReader::new(input).into_drop_guard();
+///     let c = consumed.clone();
+///     r.set_on_drop(Box::new(move |stats| c.store(stats.position(), Ordering::SeqCst)));
+///
+///     r.read_exact(&mut [0u8; 4])?;
+///     drop(r);
+///     assert_eq!(consumed.load(Ordering::SeqCst), 4);
+///     Ok(())
+/// }
+/// ```
+pub struct DropGuard<R, B>
+where
+    R: Read,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    // This is `None` only after moved out by `into_inner()`.
+    reader: Option<EnsuredBufReader<R, B>>,
+    on_drop: Option<DropHook>,
+    zero_on_drop: bool,
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> DropGuard<R, B> {
+    /// Sets a hook called once when the guard is dropped.
+    ///
+    /// The hook receives [`FinalStats`](struct.FinalStats.html) of the reader.
+    /// Previous hook is replaced without being called.
+    pub fn set_on_drop(&mut self, hook: DropHook) {
+        self.on_drop = Some(hook);
+    }
+
+    /// Sets whether the buffer is overwritten with zeros when the guard is dropped.
+    ///
+    /// This avoids leaving sensitive data in external or reused buffer. Default is `false`.
+    /// Zeroing is done after the hook set by [`.set_on_drop()`](#method.set_on_drop).
+    ///
+    /// **Note**: Only current buffer is overwritten.
+    /// Bytes left in spare capacity of `Vec<u8>` by [`.set_auto_shrink()`](struct.EnsuredBufReader.html#method.set_auto_shrink) are not.
+    pub fn set_zero_on_drop(&mut self, enabled: bool) {
+        self.zero_on_drop = enabled;
+    }
+
+    /// Gets a reference to underlying `EnsuredBufReader`.
+    pub fn get_ref(&self) -> &EnsuredBufReader<R, B> {
+        self.reader.as_ref().expect("reader is moved out.")
+    }
+
+    /// Gets a mutable reference to underlying `EnsuredBufReader`.
+    pub fn get_mut(&mut self) -> &mut EnsuredBufReader<R, B> {
+        self.reader.as_mut().expect("reader is moved out.")
+    }
+
+    /// Returns underlying `EnsuredBufReader` without running the hook or zeroing.
+    pub fn into_inner(mut self) -> EnsuredBufReader<R, B> {
+        self.reader.take().expect("reader is moved out.")
+    }
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Drop for DropGuard<R, B> {
+    fn drop(&mut self) {
+        let reader = match self.reader {
+            Some(ref mut reader) => reader,
+            None => return,
+        };
+        if let Some(hook) = self.on_drop.take() {
+            hook(&FinalStats {
+                position: reader.position,
+                consumed: reader.consumed,
+                buffered_bytes: reader.current_bytes(),
+                capacity: reader.get_capacity(),
+            });
+        }
+        if self.zero_on_drop {
+            for b in reader.buf.as_mut().iter_mut() {
+                // Volatile write is not removed even if the buffer is freed soon.
+                unsafe { ptr::write_volatile(b, 0) };
+            }
+            atomic::compiler_fence(Ordering::SeqCst);
+        }
+    }
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Read for DropGuard<R, B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.get_mut().read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        self.get_mut().read_vectored(bufs)
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.get_mut().read_to_end(buf)
+    }
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> BufRead for DropGuard<R, B> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.get_mut().fill_buf()
+    }
+
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.get_mut().read_until(byte, buf)
+    }
+
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.get_mut().read_line(buf)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.get_mut().consume(amt)
+    }
+}
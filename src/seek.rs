@@ -0,0 +1,239 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::checkpoint::restore_checkpoints;
+use crate::EnsuredBufReader;
+
+impl<R: Read + Seek, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Returns current offset of underlying reader.
+    ///
+    /// Underlying reader is ahead of consumed bytes by buffered bytes, so
+    /// `inner_position() - current_bytes()` is the offset of the next byte to be read.
+    /// This is equal to [`.position()`](#method.position) if underlying reader started at offset 0
+    /// and no bytes were discarded by [`.truncate_buffered()`](#method.truncate_buffered).
+    ///
+    /// # Errors
+    ///
+    /// Errors from seeking underlying reader are returned as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Cursor};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let mut r = EnsuredBufReader::new(Cursor::new(vec![0u8; 1000]));
+    ///
+    ///     r.fill_buf()?;
+    ///     r.consume(10);
+    ///     assert_eq!(r.position(), 10);
+    ///     assert_eq!(r.inner_position()?, 1000);
+    ///     assert_eq!(r.inner_position()? - r.current_bytes() as u64, r.position());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn inner_position(&mut self) -> io::Result<u64> {
+        self.inner.seek(SeekFrom::Current(0))
+    }
+
+    /// Returns offset of the next byte to be read, without discarding the buffer.
+    ///
+    /// This is `inner_position() - current_bytes()`, unlike [`seek(SeekFrom::Current(0))`](#method.seek) that discards the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Errors from seeking underlying reader are returned as is.
+    /// Returns error that has `.kind() == ErrorKind::InvalidData` if offset of underlying reader is smaller than count of buffered bytes,
+    /// e.g. because underlying reader is seeked directly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Cursor};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let mut r = EnsuredBufReader::new(Cursor::new(vec![0u8; 1000]));
+    ///
+    ///     r.fill_buf()?;
+    ///     r.consume(10);
+    ///     assert_eq!(r.stream_position()?, 10);
+    ///     assert_eq!(r.current_bytes(), 990);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn stream_position(&mut self) -> io::Result<u64> {
+        let inner_position = self.inner_position()?;
+        inner_position
+            .checked_sub(self.current_bytes() as u64)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "underlying reader is behind buffered bytes.",
+                )
+            })
+    }
+
+    /// Moves back to the position recorded by [`.mark()`](#method.mark).
+    ///
+    /// If the marked byte is still in the buffer, this just repositions in the buffer without I/O.
+    /// Otherwise, underlying reader is seeked back and the buffer is discarded.
+    ///
+    /// Checkpoints reached since [`.mark()`](#method.mark) are restored, as they are tied to [`.position()`](#method.position).
+    /// So replayed bytes are verified again.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if no mark is set.
+    /// Errors from seeking underlying reader are returned as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Cursor, Read};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let mut r = EnsuredBufReader::new(Cursor::new(b"abcdef".to_vec()));
+    ///
+    ///     let mut buf = [0u8; 2];
+    ///     r.read_exact(&mut buf)?;
+    ///     r.mark();
+    ///     r.read_exact(&mut buf)?;
+    ///     assert_eq!(&buf, b"cd");
+    ///
+    ///     r.reset_to_mark()?;
+    ///     r.read_exact(&mut buf)?;
+    ///     assert_eq!(&buf, b"cd");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn reset_to_mark(&mut self) -> io::Result<()> {
+        let mark = match self.mark {
+            Some(mark) => mark,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no mark is set.",
+                ));
+            }
+        };
+        if !self.rewind_in_buffer(mark) {
+            // Underlying reader is ahead of logical position by buffered bytes.
+            let inner_position = self.position + self.current_bytes() as u64;
+            let offset = to_i64(inner_position)? - to_i64(mark)?;
+            self.inner.seek(SeekFrom::Current(-offset))?;
+            self.pos = 0;
+            self.cap = 0;
+            self.position = mark;
+            self.eof = false;
+        }
+        restore_checkpoints(&mut self.checkpoints, &self.reached_checkpoints, mark);
+        // Restored checkpoints are recorded again when they are reached.
+        self.reached_checkpoints.clear();
+        Ok(())
+    }
+
+    /// Reads bytes at `offset` of underlying reader into `out`, without changing position of this reader.
+    ///
+    /// `offset` is absolute offset of underlying reader, as [`.inner_position()`](#method.inner_position).
+    /// Bytes are read directly from underlying reader until `out` is filled or EOF is reached,
+    /// then underlying reader is seeked back. Buffered bytes survive, so subsequent reads continue as if this was not called.
+    /// Returns count of read bytes.
+    ///
+    /// # Errors
+    ///
+    /// Errors from reading or seeking underlying reader are returned as is.
+    /// Underlying reader is seeked back even if reading fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Cursor, Read};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let mut r = EnsuredBufReader::new(Cursor::new(b"header:payload".to_vec()));
+    ///
+    ///     let mut buf = [0u8; 6];
+    ///     r.read_exact(&mut buf)?;
+    ///
+    ///     let mut payload = [0u8; 16];
+    ///     assert_eq!(r.read_at(7, &mut payload)?, 7);
+    ///     assert_eq!(&payload[..7], b"payload");
+    ///
+    ///     let mut rest = String::new();
+    ///     r.read_to_string(&mut rest)?;
+    ///     assert_eq!(rest, ":payload");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_at(&mut self, offset: u64, out: &mut [u8]) -> io::Result<usize> {
+        let saved = self.inner.seek(SeekFrom::Current(0))?;
+        self.inner.seek(SeekFrom::Start(offset))?;
+        let read = read_fully(&mut self.inner, out);
+        self.inner.seek(SeekFrom::Start(saved))?;
+        read
+    }
+
+    fn discard_for_seek(&mut self) {
+        self.pos = 0;
+        self.cap = 0;
+        self.mark = None;
+        self.checkpoints.clear();
+        self.reached_checkpoints.clear();
+        self.eof = false;
+    }
+}
+
+impl<R: Read + Seek, B: AsRef<[u8]> + AsMut<[u8]>> Seek for EnsuredBufReader<R, B> {
+    /// Seeks underlying reader, and discards the buffer.
+    ///
+    /// `SeekFrom::Current(n)` is relative to the logical position, i.e. next byte that `read()` or `fill_buf()` yields.
+    /// So `seek(SeekFrom::Current(0))` returns offset of the next byte, but discards the buffer.
+    ///
+    /// After seeking, [`.position()`](#method.position) is the returned offset, and mark and checkpoints are cleared.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let result = if let SeekFrom::Current(n) = pos {
+            let remainder = self.current_bytes() as i64;
+            match n.checked_sub(remainder) {
+                Some(offset) => self.inner.seek(SeekFrom::Current(offset))?,
+                None => {
+                    // Seek back to logical position first, to avoid overflow.
+                    self.inner.seek(SeekFrom::Current(-remainder))?;
+                    self.discard_for_seek();
+                    self.inner.seek(SeekFrom::Current(n))?
+                }
+            }
+        } else {
+            self.inner.seek(pos)?
+        };
+        self.discard_for_seek();
+        self.position = result;
+        Ok(result)
+    }
+}
+
+// Reads until `out` is filled or EOF is reached.
+fn read_fully<R: Read>(r: &mut R, out: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < out.len() {
+        match r.read(&mut out[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+fn to_i64(v: u64) -> io::Result<i64> {
+    if v > i64::max_value() as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "position is too large to seek.",
+        ));
+    }
+    Ok(v as i64)
+}
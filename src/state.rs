@@ -0,0 +1,111 @@
+use std::io::{self, BufRead, Read};
+
+use crate::EnsuredBufReader;
+
+/// State of `EnsuredBufReader` returned by [`.state()`](struct.EnsuredBufReader.html#method.state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReaderState {
+    /// Count of unconsumed bytes in buffer.
+    Buffered(usize),
+    /// Buffer is empty, but underlying reader is not at EOF. More bytes may become readable later.
+    Drained,
+    /// Buffer is empty and underlying reader reached EOF.
+    Eof,
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Returns whether bytes are buffered, more bytes may come, or the stream is done.
+    ///
+    /// If the buffer is empty and EOF is not observed yet, probes underlying reader by filling the buffer.
+    /// EOF is cached, so this doesn't read again once EOF is observed.
+    ///
+    /// `ReaderState::Drained` is returned only if filling stops without EOF,
+    /// e.g. by `WouldBlock` with [`.set_treat_wouldblock_as_eof_for_fill()`](#method.set_treat_wouldblock_as_eof_for_fill).
+    ///
+    /// # Errors
+    ///
+    /// Errors from underlying reader are returned as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::{EnsuredBufReader, ReaderState};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abc";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     assert_eq!(r.state()?, ReaderState::Buffered(3));
+    ///     r.consume(3);
+    ///     assert_eq!(r.state()?, ReaderState::Eof);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn state(&mut self) -> io::Result<ReaderState> {
+        if self.current_bytes() == 0 && !self.eof {
+            self.fill_buf()?;
+        }
+
+        let n = self.current_bytes();
+        if n > 0 {
+            Ok(ReaderState::Buffered(n))
+        } else if self.eof {
+            Ok(ReaderState::Eof)
+        } else {
+            Ok(ReaderState::Drained)
+        }
+    }
+
+    /// Returns whether EOF of underlying reader is observed and latched.
+    ///
+    /// Once this is `true`, `fill_buf()` and `read()` never call `read()` of underlying reader,
+    /// and return EOF after buffered bytes are consumed, like `FusedIterator`.
+    /// This is cleared only by seeking underlying reader, e.g. by [`.reset_to_mark()`](#method.reset_to_mark).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abc";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///     assert!(!r.is_fused());
+    ///
+    ///     r.read_to_end(&mut Vec::new())?;
+    ///     assert!(r.is_fused());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn is_fused(&self) -> bool {
+        self.eof
+    }
+
+    /// Returns whether EOF of underlying reader is observed and all buffered bytes are consumed.
+    ///
+    /// Unlike [`.state()`](#method.state), this never reads, so it is `false` until a fill observes EOF.
+    /// `true` means `fill_buf()` returns empty slice without calling underlying reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"abc";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     r.fill_buf()?;
+    ///     assert!(!r.is_eof());
+    ///     r.consume(3);
+    ///     assert!(r.is_eof());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn is_eof(&self) -> bool {
+        self.eof && self.current_bytes() == 0
+    }
+}
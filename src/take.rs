@@ -0,0 +1,108 @@
+use std::cmp;
+use std::io::{self, BufRead, Read};
+
+use crate::EnsuredBufReader;
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Converts into a reader that delivers at most `limit` bytes.
+    ///
+    /// Unlike `Read::take()`, returned reader keeps filling to _ensured_ size,
+    /// and `fill_buf()` returns up to `limit` remaining bytes of the buffer.
+    /// Bytes after the limit are kept buffered, and can be read after [`EnsuredTake::into_inner()`](struct.EnsuredTake.html#method.into_inner).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Read};
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"headbody";
+    ///     let mut r = EnsuredBufReader::new(input).take_ensured(4);
+    ///
+    ///     assert_eq!(r.fill_buf()?, b"head");
+    ///     r.consume(4);
+    ///     assert_eq!(r.fill_buf()?, b"");
+    ///
+    ///     let mut rest = String::new();
+    ///     r.into_inner().read_to_string(&mut rest)?;
+    ///     assert_eq!(rest, "body");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn take_ensured(self, limit: u64) -> EnsuredTake<R, B> {
+        EnsuredTake {
+            reader: self,
+            limit,
+        }
+    }
+}
+
+/// A reader that delivers limited count of bytes, keeping `BufRead` of `EnsuredBufReader`.
+///
+/// This struct is created by [`.take_ensured()`](struct.EnsuredBufReader.html#method.take_ensured).
+pub struct EnsuredTake<R, B>
+where
+    R: Read,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    reader: EnsuredBufReader<R, B>,
+    limit: u64,
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredTake<R, B> {
+    /// Returns count of bytes remaining to be delivered.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Gets a reference to underlying `EnsuredBufReader`.
+    pub fn get_ref(&self) -> &EnsuredBufReader<R, B> {
+        &self.reader
+    }
+
+    /// Returns underlying `EnsuredBufReader`, including bytes buffered after the limit.
+    pub fn into_inner(self) -> EnsuredBufReader<R, B> {
+        self.reader
+    }
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Read for EnsuredTake<R, B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Filling to ensured size is needless if buffered bytes can be returned, as `EnsuredBufReader` does.
+        let n = if self.reader.current_bytes() > 0 {
+            let available = self.reader.buffer();
+            let n = cmp::min(available.len() as u64, self.limit) as usize;
+            (&available[..n]).read(buf)?
+        } else {
+            self.fill_buf()?.read(buf)?
+        };
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> BufRead for EnsuredTake<R, B> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.limit == 0 {
+            return Ok(&[]);
+        }
+        // Needless to wait for bytes after the limit.
+        let buf = if self.limit < self.reader.effective_ensured_size() as u64 {
+            self.reader.fill_buf_to_expected_size(self.limit as usize)?
+        } else {
+            self.reader.fill_buf()?
+        };
+        let n = cmp::min(buf.len() as u64, self.limit) as usize;
+        Ok(&buf[..n])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        assert!(
+            amt as u64 <= self.limit,
+            "the amt must be <= the number of bytes in the buffer returned by fill_buf."
+        );
+        self.limit -= amt as u64;
+        self.reader.consume(amt);
+    }
+}
@@ -0,0 +1,82 @@
+//! Mock readers for testing code built on `EnsuredBufReader`.
+//!
+//! This module is available with `testutil` feature.
+
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+/// Returns a reader that yields at most `chunk` bytes of `bytes` per `read()` call.
+///
+/// # Panics
+///
+/// Panics if `chunk` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// use ensured_bufreader::testutil::partial_reader;
+///
+/// let mut r = partial_reader(b"abcde".to_vec(), 2);
+/// let mut buf = [0u8; 8];
+/// assert_eq!(r.read(&mut buf).unwrap(), 2);
+/// assert_eq!(r.read(&mut buf).unwrap(), 2);
+/// assert_eq!(r.read(&mut buf).unwrap(), 1);
+/// assert_eq!(r.read(&mut buf).unwrap(), 0);
+/// ```
+pub fn partial_reader<T: AsRef<[u8]>>(bytes: T, chunk: usize) -> impl Read {
+    PartialReader::new(bytes, chunk)
+}
+
+/// Returns a seekable reader that yields at most `chunk` bytes of `bytes` per `read()` call.
+///
+/// # Panics
+///
+/// Panics if `chunk` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Read, Seek, SeekFrom};
+/// use ensured_bufreader::testutil::seekable_partial_reader;
+///
+/// let mut r = seekable_partial_reader(b"abcde".to_vec(), 2);
+/// let mut buf = [0u8; 8];
+/// r.seek(SeekFrom::Start(3)).unwrap();
+/// assert_eq!(r.read(&mut buf).unwrap(), 2);
+/// assert_eq!(&buf[..2], b"de");
+/// ```
+pub fn seekable_partial_reader<T: AsRef<[u8]>>(bytes: T, chunk: usize) -> impl Read + Seek {
+    PartialReader::new(bytes, chunk)
+}
+
+struct PartialReader<T> {
+    cursor: Cursor<T>,
+    chunk: usize,
+}
+
+impl<T: AsRef<[u8]>> PartialReader<T> {
+    fn new(bytes: T, chunk: usize) -> PartialReader<T> {
+        assert_ne!(chunk, 0, "'chunk' must be positive.");
+        PartialReader {
+            cursor: Cursor::new(bytes),
+            chunk,
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Read for PartialReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = if buf.len() < self.chunk {
+            buf.len()
+        } else {
+            self.chunk
+        };
+        self.cursor.read(&mut buf[..n])
+    }
+}
+
+impl<T: AsRef<[u8]>> Seek for PartialReader<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.cursor.seek(pos)
+    }
+}
@@ -0,0 +1,171 @@
+use std::cmp;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncBufRead, AsyncRead, ReadBuf};
+
+use crate::{check_sizes, is_retried, make_room, DEFAULT_BUFFER_SIZE, DEFAULT_ENSURED_BYTES};
+
+/// An asynchronous version of `EnsuredBufReader` for `tokio`, that ensures _ensured_ bytes in buffer.
+///
+/// `poll_fill_buf()` keeps reading underlying reader until _ensured_ bytes are buffered or EOF is reached.
+/// If underlying reader returns `Poll::Pending` before that, `Poll::Pending` is returned and bytes read so far are kept.
+/// Like `EnsuredBufReader`, this is fused once underlying reader returns EOF.
+///
+/// Underlying reader must be `Unpin`. Use `Box::pin()` for other readers.
+///
+/// This is available with `tokio` feature.
+///
+/// # Examples
+///
+/// ```
+/// use ensured_bufreader::AsyncEnsuredBufReader;
+/// use tokio::io::{AsyncBufReadExt, AsyncRead};
+///
+/// async fn is_elf<R: AsyncRead + Unpin>(inner: R) -> std::io::Result<bool> {
+///     let mut r = AsyncEnsuredBufReader::with_capacity_and_ensured_size(16, 4, inner);
+///     // At least 4 bytes are buffered unless the stream is shorter.
+///     Ok(r.fill_buf().await?.starts_with(b"\x7fELF"))
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AsyncEnsuredBufReader<R> {
+    inner: R,
+    buf: Vec<u8>,
+    pos: usize,
+    cap: usize,
+    ensured_size: usize,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncEnsuredBufReader<R> {
+    /// Creates a new `AsyncEnsuredBufReader` with default _capacity_ and _ensured_ size.
+    pub fn new(inner: R) -> AsyncEnsuredBufReader<R> {
+        AsyncEnsuredBufReader::with_capacity_and_ensured_size(
+            DEFAULT_BUFFER_SIZE,
+            DEFAULT_ENSURED_BYTES,
+            inner,
+        )
+    }
+
+    /// Creates a new `AsyncEnsuredBufReader` with a specified `capacity` and `ensured_size`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is smaller than `ensured_size`.
+    /// Panics if `ensured_size` is 0.
+    pub fn with_capacity_and_ensured_size(
+        capacity: usize,
+        ensured_size: usize,
+        inner: R,
+    ) -> AsyncEnsuredBufReader<R> {
+        if let Err(e) = check_sizes(capacity, ensured_size) {
+            panic!("{}", e);
+        }
+        AsyncEnsuredBufReader {
+            inner,
+            buf: vec![0; capacity],
+            pos: 0,
+            cap: 0,
+            ensured_size,
+            eof: false,
+        }
+    }
+
+    /// Returns buffered bytes.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.cap]
+    }
+
+    /// Returns _capacity_ of this reader.
+    pub fn get_capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns _ensured_ size of this reader.
+    pub fn get_ensured_size(&self) -> usize {
+        self.ensured_size
+    }
+
+    /// Gets a reference to underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to underlying reader.
+    ///
+    /// **Note**: Reading directly from underlying reader desynchronizes the buffer.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `AsyncEnsuredBufReader`, returning underlying reader.
+    ///
+    /// **Note**: Buffered bytes are lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncEnsuredBufReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        // Filling to ensured size is needless if buffered bytes can be returned.
+        if this.pos == this.cap {
+            if let Poll::Ready(Err(e)) = Pin::new(&mut *this).poll_fill_buf(cx) {
+                return Poll::Ready(Err(e));
+            }
+            if this.pos == this.cap && !this.eof {
+                return Poll::Pending;
+            }
+        }
+        let n = cmp::min(out.remaining(), this.cap - this.pos);
+        out.put_slice(&this.buf[this.pos..this.pos + n]);
+        this.pos += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncBufRead for AsyncEnsuredBufReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        if this.cap - this.pos < this.ensured_size {
+            make_room(
+                &mut this.buf,
+                &mut this.pos,
+                &mut this.cap,
+                this.ensured_size,
+            );
+        }
+        while !this.eof && this.cap - this.pos < this.ensured_size {
+            let mut read_buf = ReadBuf::new(&mut this.buf[this.cap..]);
+            match Pin::new(&mut this.inner).poll_read(cx, &mut read_buf) {
+                Poll::Ready(Ok(())) => {
+                    let n = read_buf.filled().len();
+                    if n == 0 {
+                        this.eof = true;
+                    }
+                    this.cap += n;
+                }
+                Poll::Ready(Err(ref e)) if is_retried(e) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(&this.buf[this.pos..this.cap]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        assert!(
+            amt <= this.cap - this.pos,
+            "the amt must be <= the number of bytes in the buffer returned by fill_buf."
+        );
+        this.pos += amt;
+    }
+}
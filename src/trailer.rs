@@ -0,0 +1,125 @@
+use std::cmp;
+use std::io::{self, BufRead, Read};
+
+use crate::EnsuredBufReader;
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Converts into a reader that delivers every byte except the last `trailer_len` bytes of the stream.
+    ///
+    /// The trailer (e.g. checksum) can be retrieved by [`TrailerReader::finish()`](struct.TrailerReader.html#method.finish)
+    /// after the payload is fully read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `trailer_len` is not smaller than _capacity_.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use ensured_bufreader::EnsuredBufReader;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"payload\x01\x02\x03\x04";
+    ///     let mut r = EnsuredBufReader::new(input).split_trailer(4);
+    ///
+    ///     let mut payload = Vec::new();
+    ///     r.read_to_end(&mut payload)?;
+    ///     assert_eq!(payload, b"payload");
+    ///
+    ///     let (_, trailer) = r.finish()?;
+    ///     assert_eq!(trailer, [1, 2, 3, 4]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn split_trailer(self, trailer_len: usize) -> TrailerReader<R, B> {
+        assert!(
+            trailer_len < self.get_capacity(),
+            "'trailer_len' ({}) must be smaller than capacity ({}).",
+            trailer_len,
+            self.get_capacity()
+        );
+        TrailerReader {
+            reader: self,
+            trailer_len,
+        }
+    }
+}
+
+/// A reader that holds back fixed-length trailer of the stream.
+///
+/// This struct is created by [`.split_trailer()`](struct.EnsuredBufReader.html#method.split_trailer).
+pub struct TrailerReader<R, B>
+where
+    R: Read,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    reader: EnsuredBufReader<R, B>,
+    trailer_len: usize,
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> TrailerReader<R, B> {
+    /// Returns length of the trailer.
+    pub fn trailer_len(&self) -> usize {
+        self.trailer_len
+    }
+
+    /// Returns underlying reader and the trailer.
+    ///
+    /// # Errors
+    ///
+    /// Returns error that has `.kind() == ErrorKind::InvalidInput` if the payload is not fully read.
+    /// Returns error that has `.kind() == ErrorKind::UnexpectedEof` if the stream is shorter than the trailer.
+    pub fn finish(mut self) -> io::Result<(R, Vec<u8>)> {
+        let trailer_len = self.trailer_len;
+        let buf = self.fill_window()?;
+        if buf.len() > trailer_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "payload is not fully read.",
+            ));
+        }
+        if buf.len() < trailer_len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stream is shorter than trailer.",
+            ));
+        }
+        let trailer = buf.to_vec();
+
+        Ok((self.reader.into_inner(), trailer))
+    }
+
+    fn fill_window(&mut self) -> io::Result<&[u8]> {
+        let expected = cmp::min(
+            self.trailer_len.saturating_add(self.reader.ensured_size),
+            self.reader.get_capacity(),
+        );
+        self.reader.fill_buf_to_expected_size(expected)
+    }
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Read for TrailerReader<R, B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> BufRead for TrailerReader<R, B> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let trailer_len = self.trailer_len;
+        let buf = self.fill_window()?;
+        let payload_len = buf.len().saturating_sub(trailer_len);
+        Ok(&buf[..payload_len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        assert!(
+            amt <= self.reader.current_bytes().saturating_sub(self.trailer_len),
+            "the amt must be <= the number of bytes in the buffer returned by fill_buf."
+        );
+        self.reader.consume(amt);
+    }
+}
@@ -0,0 +1,123 @@
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+
+use crate::EnsuredBufReader;
+
+impl<R: Read, B: AsRef<[u8]> + AsMut<[u8]>> EnsuredBufReader<R, B> {
+    /// Runs `f` as a transaction, undoing its consumes if it returns `Err(Rollback)`.
+    ///
+    /// `f` reads through [`Transaction`](struct.Transaction.html), which provides only reading and consuming.
+    /// Rollback is done within the buffer without I/O, so it works for non-seekable readers,
+    /// but only if rolled back bytes are not discarded by the buffer shift of a fill.
+    /// To keep them, fill the buffer with enough bytes before the transaction, e.g. by [`.fill_buf_to_expected_size()`](#method.fill_buf_to_expected_size).
+    /// Checkpoints reached in rolled back consumes are restored, so replayed bytes are verified again.
+    ///
+    /// # Errors
+    ///
+    /// Errors returned by `f` are returned as is, and consumed bytes are left consumed.
+    /// Returns error that has `.kind() == ErrorKind::InvalidData` and wraps [`RollbackLostError`](struct.RollbackLostError.html)
+    /// if rolled back bytes are discarded from the buffer.
+    /// In this case, bytes consumed by `f` are left consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Read};
+    /// use ensured_bufreader::{EnsuredBufReader, Rollback};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let input: &[u8] = b"foo bar";
+    ///     let mut r = EnsuredBufReader::new(input);
+    ///
+    ///     let parsed = r.transaction(|t| {
+    ///         let mut word = [0u8; 3];
+    ///         t.read_exact(&mut word)?;
+    ///         Ok(if &word == b"bar" { Ok(word) } else { Err(Rollback) })
+    ///     })?;
+    ///     assert_eq!(parsed, Err(Rollback));
+    ///     assert_eq!(r.position(), 0);
+    ///     assert_eq!(r.fill_buf()?, b"foo bar");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn transaction<T, F>(&mut self, f: F) -> io::Result<Result<T, Rollback>>
+    where
+        F: FnOnce(&mut Transaction<'_, R, B>) -> io::Result<Result<T, Rollback>>,
+    {
+        let start = self.position;
+        let checkpoints = self.checkpoints.clone();
+        let result = f(&mut Transaction {
+            reader: self,
+            start,
+        })?;
+        if result.is_err() {
+            if !self.rewind_in_buffer(start) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    RollbackLostError(),
+                ));
+            }
+            self.checkpoints = checkpoints;
+        }
+        Ok(result)
+    }
+}
+
+/// Restricted handle of `EnsuredBufReader` passed to [`.transaction()`](struct.EnsuredBufReader.html#method.transaction).
+pub struct Transaction<'a, R, B>
+where
+    R: Read,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    reader: &'a mut EnsuredBufReader<R, B>,
+    start: u64,
+}
+
+impl<'a, R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Transaction<'a, R, B> {
+    /// Returns count of bytes consumed in this transaction.
+    pub fn consumed(&self) -> u64 {
+        self.reader.position - self.start
+    }
+
+    /// Same as [`EnsuredBufReader::fill_buf_to_expected_size()`](struct.EnsuredBufReader.html#method.fill_buf_to_expected_size).
+    pub fn fill_buf_to_expected_size(&mut self, expected_size: usize) -> io::Result<&[u8]> {
+        self.reader.fill_buf_to_expected_size(expected_size)
+    }
+}
+
+impl<'a, R: Read, B: AsRef<[u8]> + AsMut<[u8]>> Read for Transaction<'a, R, B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl<'a, R: Read, B: AsRef<[u8]> + AsMut<[u8]>> BufRead for Transaction<'a, R, B> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+/// A marker returned from a transaction to undo it.
+///
+/// See [`.transaction()`](struct.EnsuredBufReader.html#method.transaction).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rollback;
+
+/// An error type returned when rolled back bytes of a transaction are discarded from the buffer.
+///
+/// See [`.transaction()`](struct.EnsuredBufReader.html#method.transaction).
+#[derive(Debug, Clone, Copy)]
+pub struct RollbackLostError();
+
+impl fmt::Display for RollbackLostError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rolled back bytes are discarded from the buffer.")
+    }
+}
+
+impl error::Error for RollbackLostError {}
@@ -1,6 +1,15 @@
+use std::cell::Cell;
 use std::io::{BufRead, ErrorKind, Read};
+use std::num::NonZeroUsize;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
-use ensured_bufreader::{EnsuredBufReader, DEFAULT_ENSURED_BYTES};
+use ensured_bufreader::{
+    AutoShrink, ConfigError, CountingReader, EnsuredBufReader, ExpectedSizeTooLargeError,
+    FinalStats, LineOutcome, LineTerminator, ReadHint, ReaderConfig, ReaderState,
+    RecordTooLongError, Rollback, RollbackLostError, Utf8LineError, DEFAULT_BUFFER_SIZE,
+    DEFAULT_ENSURED_BYTES, NON_STRICT_EOF_RETRIES,
+};
 
 #[test]
 #[should_panic]
@@ -9,6 +18,43 @@ fn capacity_is_smaller_than_ensure_not_allowed() {
     let _ = EnsuredBufReader::with_capacity_and_ensured_size(100, 101, r);
 }
 
+#[test]
+fn with_capacity_uses_default_ensured_size() {
+    let input: &[u8] = &[0u8; 1000];
+    let r = EnsuredBufReader::with_capacity(256, input);
+    assert_eq!(r.get_capacity(), 256);
+    assert_eq!(r.get_ensured_size(), DEFAULT_ENSURED_BYTES);
+}
+
+#[test]
+#[should_panic]
+fn capacity_is_smaller_than_default_ensure_not_allowed() {
+    let r: &[u8] = &[];
+    let _ = EnsuredBufReader::with_capacity(DEFAULT_ENSURED_BYTES - 1, r);
+}
+
+#[test]
+fn with_ensured_size_uses_default_capacity() {
+    let input: &[u8] = &[0u8; 1000];
+    let mut r = EnsuredBufReader::with_ensured_size(300, input);
+    assert_eq!(r.get_capacity(), DEFAULT_BUFFER_SIZE);
+    assert!(r.fill_buf().unwrap().len() >= 300);
+}
+
+#[test]
+#[should_panic]
+fn ensure_is_0_not_allowed_with_ensured_size() {
+    let r: &[u8] = &[];
+    let _ = EnsuredBufReader::with_ensured_size(0, r);
+}
+
+#[test]
+#[should_panic]
+fn ensure_is_larger_than_default_capacity_not_allowed() {
+    let r: &[u8] = &[];
+    let _ = EnsuredBufReader::with_ensured_size(DEFAULT_BUFFER_SIZE + 1, r);
+}
+
 #[test]
 #[should_panic]
 fn ensure_is_0_not_allowed_with_capacity_and_ensure() {
@@ -154,3 +200,2332 @@ fn fill_buf_to_expected_size_returns_error_when_expected_size_is_too_large() {
     let err = r.fill_buf_to_expected_size(513).unwrap_err();
     assert_eq!(err.kind(), ErrorKind::InvalidInput);
 }
+
+struct WouldBlockAfter<'a> {
+    chunks: Vec<&'a [u8]>,
+}
+
+impl<'a> Read for WouldBlockAfter<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.chunks.is_empty() {
+            return Err(std::io::Error::from(ErrorKind::WouldBlock));
+        }
+        let chunk = self.chunks.remove(0);
+        buf[..chunk.len()].copy_from_slice(chunk);
+        Ok(chunk.len())
+    }
+}
+
+#[test]
+fn fill_buf_returns_wouldblock_by_default() {
+    let inner = WouldBlockAfter {
+        chunks: vec![b"abc"],
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 8, inner);
+
+    let err = r.fill_buf().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert_eq!(r.buffer(), b"abc");
+}
+
+#[test]
+fn fill_buf_stops_at_wouldblock_if_enabled() {
+    let inner = WouldBlockAfter {
+        chunks: vec![b"abc", b"de"],
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 8, inner);
+    r.set_treat_wouldblock_as_eof_for_fill(true);
+
+    assert_eq!(r.fill_buf().unwrap(), b"abcde");
+    r.consume(5);
+    assert_eq!(r.fill_buf().unwrap(), b"");
+}
+
+#[test]
+fn utilization_follows_buffered_bytes() {
+    let input = [0u8; 100];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(200, 10, &input[..]);
+    assert_eq!(r.utilization(), 0.0);
+
+    r.fill_buf().unwrap();
+    assert_eq!(r.utilization(), 0.5);
+
+    r.consume(50);
+    assert_eq!(r.utilization(), 0.25);
+}
+
+#[test]
+fn read_line_with_terminator_keeps_existing_content() {
+    let input: &[u8] = b"\r\nab\r\n\n";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 1, input);
+
+    let mut line = b"x".to_vec();
+    assert_eq!(
+        r.read_line_with_terminator(&mut line).unwrap(),
+        (2, LineTerminator::CrLf)
+    );
+    assert_eq!(line, b"x");
+
+    assert_eq!(
+        r.read_line_with_terminator(&mut line).unwrap(),
+        (4, LineTerminator::CrLf)
+    );
+    assert_eq!(line, b"xab");
+
+    assert_eq!(
+        r.read_line_with_terminator(&mut line).unwrap(),
+        (1, LineTerminator::Lf)
+    );
+    assert_eq!(line, b"xab");
+
+    assert_eq!(
+        r.read_line_with_terminator(&mut line).unwrap(),
+        (0, LineTerminator::None)
+    );
+    assert_eq!(line, b"xab");
+}
+
+#[test]
+fn auto_grow_grows_up_to_max_capacity() {
+    let input = vec![1u8; 4096];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(512, 16, input.as_slice());
+    r.set_auto_grow(true);
+    r.set_max_capacity(2048);
+
+    r.fill_buf().unwrap();
+    r.consume(10);
+    let buf = r.fill_buf_to_expected_size(2048).unwrap();
+    assert_eq!(buf.len(), 2048);
+    assert_eq!(r.get_capacity(), 2048);
+
+    let err = r.fill_buf_to_expected_size(2049).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    r.set_auto_grow(false);
+    let err = r.fill_buf_to_expected_size(2049).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn auto_shrink_keeps_unconsumed_bytes() {
+    let input: Vec<u8> = (0..=255).cycle().take(4096).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(256, 4, input.as_slice());
+    r.set_auto_grow(true);
+    r.set_auto_shrink(Some(AutoShrink {
+        baseline: 64,
+        after_small_fills: 1,
+    }));
+
+    let mut read_bytes = r.fill_buf_to_expected_size(1024).unwrap()[..1020].to_vec();
+    assert_eq!(r.get_capacity(), 1024);
+    r.consume(1020);
+
+    r.read_to_end(&mut read_bytes).unwrap();
+    assert_eq!(input, read_bytes);
+    assert_eq!(r.get_capacity(), 64);
+}
+
+#[test]
+#[should_panic]
+fn auto_shrink_baseline_smaller_than_ensured_size_not_allowed() {
+    let r: &[u8] = &[];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(256, 128, r);
+    r.set_auto_shrink(Some(AutoShrink {
+        baseline: 64,
+        after_small_fills: 1,
+    }));
+}
+
+#[test]
+fn auto_shrink_baseline_equal_to_ensured_size() {
+    let input: Vec<u8> = (0..=255).cycle().take(1024).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 64, input.as_slice());
+    r.set_auto_shrink(Some(AutoShrink {
+        baseline: 64,
+        after_small_fills: 1,
+    }));
+    r.fill_buf().unwrap();
+    r.consume(1000);
+
+    // Only 24 bytes are left, so this fill is small and shrinks the buffer to exactly ensured size.
+    assert_eq!(r.fill_buf().unwrap(), &input[1000..]);
+    assert_eq!(r.get_capacity(), 64);
+}
+
+#[test]
+fn auto_shrink_baseline_follows_ensured_size() {
+    let input: &[u8] = &[0; 1024];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 16, input);
+    r.set_auto_shrink(Some(AutoShrink {
+        baseline: 64,
+        after_small_fills: 1,
+    }));
+    r.set_ensured_size(128).unwrap();
+    r.fill_buf().unwrap();
+    r.consume(1000);
+
+    assert_eq!(r.fill_buf().unwrap().len(), 24);
+    assert_eq!(r.get_capacity(), 128);
+}
+
+struct Chunked<'a> {
+    data: &'a [u8],
+    chunk: usize,
+}
+
+impl<'a> Read for Chunked<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.chunk.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn split_trailer_holds_back_trailer() {
+    let input: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+    for &chunk in &[1, 3, 7, 64, 1000] {
+        let inner = Chunked {
+            data: &input,
+            chunk,
+        };
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 2, inner).split_trailer(6);
+
+        let mut payload = Vec::new();
+        r.read_to_end(&mut payload).unwrap();
+        assert_eq!(&payload[..], &input[..994], "chunk = {}", chunk);
+
+        let (_, trailer) = r.finish().unwrap();
+        assert_eq!(&trailer[..], &input[994..], "chunk = {}", chunk);
+    }
+}
+
+#[test]
+fn split_trailer_finish_fails_before_payload_is_read() {
+    let input: &[u8] = b"0123456789";
+    let r = EnsuredBufReader::new(input).split_trailer(4);
+    let err = r.finish().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let input: &[u8] = b"012";
+    let r = EnsuredBufReader::new(input).split_trailer(4);
+    let err = r.finish().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn read_exact_bypassing_reads_larger_than_capacity() {
+    let input: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 7,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, inner);
+
+    let mut head = [0u8; 10];
+    r.read_exact_bypassing(&mut head).unwrap();
+    assert_eq!(&head[..], &input[..10]);
+
+    let mut large = [0u8; 500];
+    r.read_exact_bypassing(&mut large).unwrap();
+    assert_eq!(&large[..], &input[10..510]);
+
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(&rest[..], &input[510..]);
+
+    let mut out = [0u8; 100];
+    let err = r.read_exact_bypassing(&mut out).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn with_prefilled_accepts_prefix_larger_than_default_capacity() {
+    let prefix = vec![7u8; 10000];
+    let inner: &[u8] = b"tail";
+    let mut r = EnsuredBufReader::with_prefilled(&prefix, 16, inner).unwrap();
+    assert_eq!(r.get_capacity(), 10000);
+    assert_eq!(r.current_bytes(), 10000);
+
+    let mut read_bytes = Vec::new();
+    r.read_to_end(&mut read_bytes).unwrap();
+    assert_eq!(&read_bytes[..10000], &prefix[..]);
+    assert_eq!(&read_bytes[10000..], b"tail");
+}
+
+#[test]
+fn with_prefilled_rejects_zero_ensured_size() {
+    let inner: &[u8] = &[];
+    let err = EnsuredBufReader::with_prefilled(b"abc", 0, inner).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn peek_up_to_does_not_consume() {
+    let input: Vec<u8> = (0..=255).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 5,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 4, inner);
+
+    let peeked = r.peek_up_to(20).unwrap().to_vec();
+    assert_eq!(peeked, &input[..20]);
+    assert_eq!(r.position(), 0);
+
+    let mut read = [0u8; 20];
+    r.read_exact(&mut read).unwrap();
+    assert_eq!(&read[..], peeked.as_slice());
+}
+
+#[test]
+fn peek_re_delivers_peeked_bytes() {
+    let input: Vec<u8> = (0..=255).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 5,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 4, inner);
+
+    let peeked = r.peek(20).unwrap().to_vec();
+    assert_eq!(peeked, &input[..20]);
+    assert_eq!(r.peek(3).unwrap(), &input[..3]);
+
+    let mut read = [0u8; 20];
+    r.read_exact(&mut read).unwrap();
+    assert_eq!(&read[..], peeked.as_slice());
+
+    let err = r.peek(65).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn peek_up_to_fills_beyond_ensured_size() {
+    let input: Vec<u8> = (0..=255).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 3,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 1, inner);
+
+    assert_eq!(r.peek_up_to(50).unwrap(), &input[..50]);
+    r.consume(40);
+    assert_eq!(r.peek_up_to(64).unwrap(), &input[40..104]);
+
+    let err = r.peek_up_to(65).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn read_until_borrowed_returns_none_for_record_spanning_fills() {
+    let input: &[u8] = b"abcdefgh,ij";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 4, input);
+
+    assert_eq!(r.read_until_borrowed(b',').unwrap(), None);
+
+    let mut record = Vec::new();
+    r.read_until(b',', &mut record).unwrap();
+    assert_eq!(record, b"abcdefgh,");
+
+    assert_eq!(r.read_until_borrowed(b',').unwrap(), None);
+    assert_eq!(r.buffer(), b"ij");
+}
+
+#[test]
+fn read_until_slice_finds_separator_split_across_fills() {
+    let mut input = vec![b'x'; 1000];
+    input.extend_from_slice(b"--");
+    input.extend_from_slice(&[b'y'; 30]);
+
+    for &chunk in &[1, 2, 3, 1001, 2000] {
+        let inner = Chunked {
+            data: &input,
+            chunk,
+        };
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(8, 1, inner);
+
+        let mut buf = Vec::new();
+        assert_eq!(r.read_until_slice(b"--", &mut buf).unwrap(), 1002);
+        assert_eq!(&buf[..], &input[..1002], "chunk = {}", chunk);
+
+        buf.clear();
+        assert_eq!(r.read_until_slice(b"--", &mut buf).unwrap(), 30);
+        assert_eq!(&buf[..], &input[1002..], "chunk = {}", chunk);
+    }
+}
+
+#[test]
+fn read_until_slice_handles_repetitive_separators() {
+    check_read_until_slice(b"aa", b"aaa", &[b"aa", b"a"]);
+    check_read_until_slice(b"aab", b"aaab", &[b"aaab"]);
+    check_read_until_slice(b"abab", b"abababx", &[b"abab", b"abx"]);
+    check_read_until_slice(
+        b"\r\n\r\n",
+        b"GET\r\nHost\r\n\r\nbody\r\r\n\r\n",
+        &[b"GET\r\nHost\r\n\r\n", b"body\r\r\n\r\n"],
+    );
+    check_read_until_slice(b"\r\n\r\n", b"\r\n\r\r\n\r\n", &[b"\r\n\r\r\n\r\n"]);
+}
+
+// Reads `records` separated by `sep` from `input`, with every chunk size of underlying reads.
+fn check_read_until_slice(sep: &[u8], input: &[u8], records: &[&[u8]]) {
+    for chunk in 1..=input.len() {
+        let inner = Chunked { data: input, chunk };
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 1, inner);
+
+        for &record in records {
+            let mut buf = Vec::new();
+            assert_eq!(r.read_until_slice(sep, &mut buf).unwrap(), record.len());
+            assert_eq!(&buf[..], record, "sep = {:?}, chunk = {}", sep, chunk);
+        }
+        let mut buf = Vec::new();
+        assert_eq!(r.read_until_slice(sep, &mut buf).unwrap(), 0);
+    }
+}
+
+struct Scripted {
+    results: Vec<std::io::Result<Vec<u8>>>,
+}
+
+impl Read for Scripted {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.results.is_empty() {
+            return Ok(0);
+        }
+        let bytes = self.results.remove(0)?;
+        buf[..bytes.len()].copy_from_slice(&bytes);
+        Ok(bytes.len())
+    }
+}
+
+#[test]
+fn read_exact_bypassing_counts_bytes_before_eof() {
+    let inner = Scripted {
+        results: vec![
+            Ok(b"abcd".to_vec()),
+            Err(std::io::Error::from(ErrorKind::Interrupted)),
+            Ok(b"0123456789".to_vec()),
+            Ok(Vec::new()),
+            Ok(b"late".to_vec()),
+        ],
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(8, 4, inner);
+    assert_eq!(r.fill_buf().unwrap(), b"abcd");
+
+    let mut out = [0u8; 20];
+    let err = r.read_exact_bypassing(&mut out).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(&out[..14], b"abcd0123456789");
+    assert_eq!(r.position(), 14);
+    assert_eq!(r.total_consumed(), 14);
+
+    // EOF is latched, so later bytes are not read.
+    assert!(r.fill_buf().unwrap().is_empty());
+    let err = r.read_exact_bypassing(&mut out).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(r.position(), 14);
+}
+
+#[test]
+fn read_exact_bypassing_counts_bytes_before_error() {
+    let inner = Scripted {
+        results: vec![
+            Ok(b"0123456789".to_vec()),
+            Err(std::io::Error::from(ErrorKind::Other)),
+        ],
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(8, 4, inner);
+
+    let mut out = [0u8; 20];
+    let err = r.read_exact_bypassing(&mut out).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(r.position(), 10);
+    assert_eq!(r.total_consumed(), 10);
+}
+
+#[test]
+fn non_strict_eof_retries_zero_reads() {
+    let script = || {
+        let mut results = vec![Ok(b"ab".to_vec())];
+        for _ in 0..NON_STRICT_EOF_RETRIES {
+            results.push(Ok(Vec::new()));
+        }
+        results.push(Ok(b"cd".to_vec()));
+        Scripted { results }
+    };
+
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 4, script());
+    assert_eq!(r.fill_buf().unwrap(), b"ab");
+
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 4, script());
+    r.set_strict_eof(false);
+    assert_eq!(r.fill_buf().unwrap(), b"abcd");
+}
+
+#[test]
+fn non_strict_eof_gives_up_at_retry_limit() {
+    let mut results = vec![Ok(b"ab".to_vec())];
+    for _ in 0..=NON_STRICT_EOF_RETRIES {
+        results.push(Ok(Vec::new()));
+    }
+    results.push(Ok(b"cd".to_vec()));
+
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 4, Scripted { results });
+    r.set_strict_eof(false);
+    assert_eq!(r.fill_buf().unwrap(), b"ab");
+}
+
+#[test]
+fn peek_byte_at_fills_across_chunks() {
+    let input: Vec<u8> = (0..100).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 3,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 1, inner);
+
+    assert_eq!(r.peek_byte_at(31).unwrap(), Some(31));
+    r.consume(30);
+    assert_eq!(r.peek_byte_at(0).unwrap(), Some(30));
+    assert_eq!(r.peek_byte_at(20).unwrap(), Some(50));
+
+    let err = r.peek_byte_at(32).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    r.consume(r.current_bytes());
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(r.peek_byte_at(0).unwrap(), None);
+}
+
+#[test]
+fn adaptive_ensured_size_stays_within_bounds() {
+    let input = vec![0u8; 1 << 20];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 16, input.as_slice());
+    r.set_adaptive(true);
+    r.set_adaptive_bounds(8, 512);
+    assert_eq!(r.effective_ensured_size(), 16);
+
+    for _ in 0..256 {
+        r.fill_buf().unwrap();
+        r.consume(1);
+    }
+    assert_eq!(r.effective_ensured_size(), 8);
+
+    for _ in 0..256 {
+        let n = r.fill_buf().unwrap().len().min(1000);
+        r.consume(n);
+    }
+    assert_eq!(r.effective_ensured_size(), 512);
+    assert_eq!(r.get_ensured_size(), 16);
+
+    r.set_adaptive(false);
+    assert_eq!(r.effective_ensured_size(), 16);
+}
+
+#[test]
+fn read_uses_buffered_bytes_without_filling() {
+    let inner = Scripted {
+        results: vec![Ok(b"abcdef".to_vec()), Ok(b"gh".to_vec())],
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 4, inner);
+    r.fill_buf().unwrap();
+    r.consume(4);
+
+    let mut buf = [0u8; 1];
+    assert_eq!(r.read(&mut buf).unwrap(), 1);
+    assert_eq!(buf[0], b'e');
+    assert_eq!(r.buffer(), b"f");
+
+    let mut buf = [0u8; 8];
+    assert_eq!(r.read(&mut buf).unwrap(), 1);
+    assert_eq!(r.read(&mut buf).unwrap(), 2);
+    assert_eq!(&buf[..2], b"gh");
+}
+
+#[test]
+fn fill_to_capacity_reads_until_full() {
+    let input: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 7,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, inner);
+    r.set_fill_to_capacity(true);
+
+    assert_eq!(r.fill_buf().unwrap(), &input[..64]);
+
+    // Enough bytes are buffered, so no fill happens.
+    r.consume(50);
+    assert_eq!(r.fill_buf().unwrap(), &input[50..64]);
+
+    r.consume(10);
+    assert_eq!(r.fill_buf().unwrap(), &input[60..124]);
+
+    let mut read_bytes = input[..60].to_vec();
+    r.read_to_end(&mut read_bytes).unwrap();
+    assert_eq!(input, read_bytes);
+}
+
+#[test]
+fn process_line_streams_line_longer_than_capacity() {
+    let mut input = vec![b'a'; 1000];
+    input.extend_from_slice(b"\nbc");
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 4, input.as_slice());
+
+    let mut calls = 0;
+    let mut line = Vec::new();
+    let found = r
+        .process_line(|chunk| {
+            calls += 1;
+            line.extend_from_slice(chunk);
+            Ok(())
+        })
+        .unwrap();
+    assert!(found);
+    assert!(calls > 1);
+    assert_eq!(&line[..], &input[..1001]);
+
+    line.clear();
+    let found = r
+        .process_line(|chunk| {
+            line.extend_from_slice(chunk);
+            Ok(())
+        })
+        .unwrap();
+    assert!(!found);
+    assert_eq!(line, b"bc");
+}
+
+#[test]
+fn available_is_buffered_bytes() {
+    let input: &[u8] = &[0u8; 100];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 8, input);
+    assert_eq!(r.available(), 0);
+
+    r.fill_buf().unwrap();
+    assert_eq!(r.available(), r.current_bytes());
+    r.consume(3);
+    assert_eq!(r.available(), r.current_bytes());
+}
+
+#[test]
+fn on_drop_receives_final_stats() {
+    let input: &[u8] = &[0u8; 100];
+    let stats: Arc<Mutex<Option<FinalStats>>> = Arc::new(Mutex::new(None));
+
+    {
+        let mut r =
+            EnsuredBufReader::with_capacity_and_ensured_size(32, 8, input).into_drop_guard();
+        let s = stats.clone();
+        r.set_on_drop(Box::new(move |st| *s.lock().unwrap() = Some(*st)));
+        r.fill_buf().unwrap();
+        r.consume(5);
+    }
+
+    let stats = stats.lock().unwrap().unwrap();
+    assert_eq!(stats.position(), 5);
+    assert_eq!(stats.buffered_bytes(), 27);
+    assert_eq!(stats.capacity(), 32);
+}
+
+#[test]
+fn drop_guard_into_inner_skips_hooks() {
+    let input: &[u8] = b"secret data";
+    let mut buf = [0xffu8; 32];
+    let called = Arc::new(Mutex::new(false));
+
+    {
+        let mut r =
+            EnsuredBufReader::from_mut_ref_and_ensured_size(&mut buf, 4, input).into_drop_guard();
+        let c = called.clone();
+        r.set_on_drop(Box::new(move |_| *c.lock().unwrap() = true));
+        r.set_zero_on_drop(true);
+        r.fill_buf().unwrap();
+
+        let mut r = r.into_inner();
+        assert_eq!(r.fill_buf().unwrap(), b"secret data");
+    }
+
+    assert!(!*called.lock().unwrap());
+    assert_eq!(&buf[..11], b"secret data");
+}
+
+#[test]
+fn on_drop_accepts_non_send_hook() {
+    let input: &[u8] = b"0123456789";
+    let consumed = Rc::new(Cell::new(0));
+
+    {
+        let mut r = EnsuredBufReader::new(input).into_drop_guard();
+        let c = consumed.clone();
+        r.set_on_drop(Box::new(move |st| c.set(st.consumed())));
+        r.read_exact(&mut [0u8; 3]).unwrap();
+    }
+
+    assert_eq!(consumed.get(), 3);
+}
+
+#[test]
+fn zero_on_drop_overwrites_external_buffer() {
+    let input: &[u8] = b"secret data";
+    let mut buf = [0xffu8; 32];
+
+    {
+        let mut r =
+            EnsuredBufReader::from_mut_ref_and_ensured_size(&mut buf, 4, input).into_drop_guard();
+        r.set_zero_on_drop(true);
+        r.fill_buf().unwrap();
+    }
+
+    assert!(buf.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn borrowed_buffer_may_be_dropped_before_reader() {
+    let input: &[u8] = b"abc";
+    let mut r;
+    let mut buf = [0u8; 256];
+    r = EnsuredBufReader::from_buffer(&mut buf[..], input);
+    assert_eq!(r.fill_buf().unwrap(), b"abc");
+}
+
+// Panics if a read larger than `limit` is requested.
+struct LimitedRead<'a> {
+    data: &'a [u8],
+    limit: usize,
+}
+
+impl<'a> Read for LimitedRead<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        assert!(buf.len() <= self.limit, "read of {} bytes", buf.len());
+        self.data.read(buf)
+    }
+}
+
+#[test]
+fn max_read_chunk_limits_each_read() {
+    let input: Vec<u8> = (0..10000).map(|i| i as u8).collect();
+    let inner = LimitedRead {
+        data: &input,
+        limit: 64,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 512, inner);
+    r.set_max_read_chunk(64);
+
+    assert_eq!(r.fill_buf().unwrap().len(), 512);
+
+    let mut out = vec![0u8; 5000];
+    r.read_exact_bypassing(&mut out).unwrap();
+    assert_eq!(&out[..], &input[..5000]);
+
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(&rest[..], &input[5000..]);
+}
+
+#[test]
+fn consume_landing_on_checkpoints_is_allowed() {
+    let input: &[u8] = &[0u8; 100];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 16, input);
+    r.push_checkpoint(20);
+    r.push_checkpoint(10);
+
+    r.fill_buf().unwrap();
+    r.consume(10);
+    r.fill_buf().unwrap();
+    r.consume(10);
+    r.fill_buf().unwrap();
+    r.consume(16);
+    assert_eq!(r.position(), 36);
+}
+
+#[test]
+#[should_panic(expected = "checkpoint")]
+fn consume_across_checkpoint_panics() {
+    let input: &[u8] = &[0u8; 100];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 16, input);
+    r.push_checkpoint(5);
+
+    r.fill_buf().unwrap();
+    r.consume(6);
+}
+
+#[test]
+fn fill_buf_mut_transforms_in_place_across_fills() {
+    let plain: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let encrypted: Vec<u8> = plain.iter().map(|b| b ^ 0x5a).collect();
+    let inner = Chunked {
+        data: &encrypted,
+        chunk: 7,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 16, inner);
+
+    let mut out = Vec::new();
+    loop {
+        let n = {
+            let buf = r.fill_buf_mut().unwrap();
+            for b in buf.iter_mut() {
+                *b ^= 0x5a;
+            }
+            buf.len()
+        };
+        if n == 0 {
+            break;
+        }
+        out.extend_from_slice(r.buffer());
+        r.consume(n);
+    }
+    assert_eq!(out, plain);
+}
+
+#[test]
+fn split_borrowed_handles_short_and_long_segments() {
+    let long = vec![b'x'; 100];
+    let mut input = Vec::new();
+    input.extend_from_slice(b"a\n");
+    input.extend_from_slice(&long);
+    input.extend_from_slice(b"\nbc\n\nd");
+
+    for &chunk in &[1, 3, 1000] {
+        let inner = Chunked {
+            data: &input,
+            chunk,
+        };
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 4, inner);
+        {
+            let mut split = r.split_borrowed(b'\n');
+            assert_eq!(split.next().unwrap(), Some(&b"a"[..]));
+            assert_eq!(split.next().unwrap(), Some(&long[..]));
+            assert_eq!(split.next().unwrap(), Some(&b"bc"[..]));
+            assert_eq!(split.next().unwrap(), Some(&b""[..]));
+        }
+        assert_eq!(r.position(), input.len() as u64 - 1);
+        let mut split = r.split_borrowed(b'\n');
+        assert_eq!(split.next().unwrap(), Some(&b"d"[..]));
+        assert_eq!(split.next().unwrap(), None);
+    }
+}
+
+#[test]
+fn split_borrowed_respects_max_record_size() {
+    let input: &[u8] = b"abc,abcdef,ab,abcdef";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 4, input);
+    r.set_max_record_size(Some(4));
+
+    let mut split = r.split_borrowed(b',');
+    assert_eq!(split.next().unwrap(), Some(&b"abc"[..]));
+    assert_record_too_long(split.next().unwrap_err(), 4);
+    assert_eq!(split.next().unwrap(), Some(&b"ef"[..]));
+    assert_eq!(split.next().unwrap(), Some(&b"ab"[..]));
+    // The last segment has no delimiter, and is limited as well.
+    assert_record_too_long(split.next().unwrap_err(), 4);
+    assert_eq!(split.next().unwrap(), Some(&b"ef"[..]));
+    assert_eq!(split.next().unwrap(), None);
+}
+
+#[test]
+fn state_distinguishes_drained_and_eof() {
+    let inner = WouldBlockAfter {
+        chunks: vec![b"abc"],
+    };
+    let mut r = EnsuredBufReader::new(inner);
+    r.set_treat_wouldblock_as_eof_for_fill(true);
+    assert_eq!(r.state().unwrap(), ReaderState::Buffered(3));
+    r.consume(3);
+    assert_eq!(r.state().unwrap(), ReaderState::Drained);
+
+    let input: &[u8] = b"abc";
+    let mut r = EnsuredBufReader::new(input);
+    r.fill_buf().unwrap();
+    r.consume(3);
+    assert_eq!(r.state().unwrap(), ReaderState::Eof);
+    assert_eq!(r.state().unwrap(), ReaderState::Eof);
+}
+
+#[test]
+fn truncate_buffered_discards_tail() {
+    let input: Vec<u8> = (0..100).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 8, input.as_slice());
+
+    assert_eq!(r.fill_buf().unwrap().len(), 32);
+    r.consume(2);
+    r.truncate_buffered(3);
+    assert_eq!(r.buffer(), &[2, 3, 4]);
+    r.consume(3);
+
+    assert_eq!(r.fill_buf().unwrap()[0], 32);
+    assert_eq!(r.position(), 5);
+}
+
+#[test]
+#[should_panic]
+fn truncate_buffered_more_than_buffered_panics() {
+    let input: &[u8] = b"abc";
+    let mut r = EnsuredBufReader::new(input);
+    r.fill_buf().unwrap();
+    r.truncate_buffered(4);
+}
+
+#[test]
+fn nonzero_ensured_size_constructors() {
+    let ensured_size = NonZeroUsize::new(16).unwrap();
+
+    let input: &[u8] = &[0u8; 100];
+    let r = EnsuredBufReader::with_capacity_and_nonzero_ensured_size(64, ensured_size, input);
+    assert_eq!(r.get_capacity(), 64);
+    assert_eq!(r.get_ensured_size(), 16);
+
+    let r = EnsuredBufReader::from_buffer_and_nonzero_ensured_size([0u8; 32], ensured_size, input);
+    assert_eq!(r.get_capacity(), 32);
+    assert_eq!(r.get_ensured_size(), 16);
+}
+
+#[test]
+fn read_until_into_rejects_too_long_record() {
+    let input: &[u8] = b"abcd\nabcde\nabcde";
+    for &chunk in &[1, 2, 100] {
+        let inner = Chunked { data: input, chunk };
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 1, inner);
+        let mut out = [0u8; 5];
+
+        assert_eq!(r.read_until_into(b'\n', &mut out).unwrap(), (5, true));
+        assert_eq!(&out, b"abcd\n");
+
+        let err = r.read_until_into(b'\n', &mut out).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let inner_err = err.get_ref().unwrap();
+        let too_long = inner_err.downcast_ref::<RecordTooLongError>().unwrap();
+        assert_eq!(too_long.limit(), 5);
+        assert_eq!(&out, b"abcde");
+        r.consume(1);
+
+        assert_eq!(r.read_until_into(b'\n', &mut out).unwrap(), (5, false));
+        assert_eq!(&out, b"abcde");
+    }
+}
+
+#[test]
+fn into_peekable_keeps_prefix_in_buffer() {
+    let input: &[u8] = b"abcdefgh";
+    let inner = Chunked {
+        data: input,
+        chunk: 1,
+    };
+    let (prefix, mut r) = EnsuredBufReader::with_capacity_and_ensured_size(8, 1, inner)
+        .into_peekable(5)
+        .unwrap();
+    assert_eq!(prefix, b"abcde");
+    assert_eq!(r.position(), 0);
+
+    let mut all = Vec::new();
+    r.read_to_end(&mut all).unwrap();
+    assert_eq!(all, input);
+
+    let input: &[u8] = b"ab";
+    let (prefix, _) = EnsuredBufReader::new(input).into_peekable(5).unwrap();
+    assert_eq!(prefix, b"ab");
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "without consume()")]
+fn detect_no_progress_panics_on_spinning_loop() {
+    let input: &[u8] = &[0u8; 100];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 8, input);
+    r.set_detect_no_progress(true);
+    loop {
+        r.fill_buf().unwrap();
+    }
+}
+
+#[test]
+fn detect_no_progress_allows_consuming_loop() {
+    let input: &[u8] = &[0u8; 100_000];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 8, input);
+    r.set_detect_no_progress(true);
+    while !r.fill_buf().unwrap().is_empty() {
+        r.consume(1);
+    }
+    for _ in 0..10_000 {
+        assert!(r.fill_buf().unwrap().is_empty());
+    }
+}
+
+#[test]
+fn read_to_end_limited_stops_at_limit() {
+    let input = vec![b'x'; 10000];
+    let inner = Chunked {
+        data: &input,
+        chunk: 100,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(256, 16, inner);
+
+    let mut buf = Vec::new();
+    let err = r.read_to_end_limited(&mut buf, 1000).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.get_ref().unwrap().is::<RecordTooLongError>());
+    assert_eq!(buf.len(), 1000);
+    assert_eq!(r.position(), 1000);
+
+    buf.clear();
+    assert_eq!(r.read_to_end_limited(&mut buf, 9000).unwrap(), 9000);
+    assert_eq!(buf.len(), 9000);
+}
+
+#[test]
+fn set_ensured_size_mid_stream() {
+    let input: Vec<u8> = (0..=255).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 3,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 4, inner);
+
+    assert_eq!(r.fill_buf().unwrap().len(), 6);
+    r.consume(1);
+
+    // Raising keeps buffered bytes and next fill reaches the new size.
+    r.set_ensured_size(32).unwrap();
+    assert_eq!(r.buffer(), &input[1..6]);
+    let buf = r.fill_buf().unwrap();
+    assert_eq!(buf.len(), 32);
+    assert_eq!(buf, &input[1..33]);
+    r.consume(30);
+
+    // Lowering takes effect on next fill without reading.
+    r.set_ensured_size(2).unwrap();
+    assert_eq!(r.fill_buf().unwrap(), &input[31..33]);
+    r.consume(2);
+    assert_eq!(r.fill_buf().unwrap(), &input[33..36]);
+
+    assert_eq!(r.set_ensured_size(0), Err(ConfigError::ZeroEnsuredSize));
+    assert_eq!(
+        r.set_ensured_size(65),
+        Err(ConfigError::CapacityTooSmall {
+            capacity: 64,
+            ensured_size: 65
+        })
+    );
+    assert_eq!(r.get_ensured_size(), 2);
+}
+
+#[test]
+fn set_ensured_size_and_compact_moves_buffered_bytes() {
+    let input: Vec<u8> = (0..=255).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, input.as_slice());
+
+    r.fill_buf().unwrap();
+    r.consume(60);
+    r.set_ensured_size_and_compact(32).unwrap();
+    assert_eq!(r.buffer(), &input[60..64]);
+    assert_eq!(r.fill_buf().unwrap().len(), 64);
+    assert_eq!(r.get_ensured_size(), 32);
+
+    assert_eq!(
+        r.set_ensured_size_and_compact(0),
+        Err(ConfigError::ZeroEnsuredSize)
+    );
+    assert_eq!(
+        r.set_ensured_size_and_compact(65),
+        Err(ConfigError::CapacityTooSmall {
+            capacity: 64,
+            ensured_size: 65
+        })
+    );
+    assert_eq!(r.get_ensured_size(), 32);
+}
+
+#[test]
+fn try_fill_buf_returns_none_on_wouldblock() {
+    let inner = WouldBlockAfter {
+        chunks: vec![b"abc"],
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 8, inner);
+    assert_eq!(r.try_fill_buf().unwrap(), None);
+    assert_eq!(r.buffer(), b"abc");
+
+    let inner = WouldBlockAfter {
+        chunks: vec![b"abcd", b"efgh"],
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 8, inner);
+    assert_eq!(r.try_fill_buf().unwrap(), Some(&b"abcdefgh"[..]));
+
+    let input: &[u8] = b"abc";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 8, input);
+    assert_eq!(r.try_fill_buf().unwrap(), Some(&b"abc"[..]));
+}
+
+#[test]
+fn compact_if_tail_below_makes_room_in_tail() {
+    let input: Vec<u8> = (0..=255).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, input.as_slice());
+
+    r.fill_buf().unwrap();
+    r.consume(60);
+    let before = r.buffer().as_ptr();
+    r.compact_if_tail_below(0);
+    assert_eq!(r.buffer().as_ptr(), before);
+
+    r.compact_if_tail_below(16);
+    assert_ne!(r.buffer().as_ptr(), before);
+    assert_eq!(r.buffer(), &input[60..64]);
+}
+
+#[test]
+fn peek_map_does_not_consume() {
+    let input: &[u8] = b"abcdef";
+    let inner = Chunked {
+        data: input,
+        chunk: 1,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(8, 1, inner);
+
+    assert_eq!(r.peek_map(4, |b| b.to_vec()).unwrap(), b"abcd");
+    assert_eq!(r.position(), 0);
+    assert_eq!(
+        r.peek_map(7, |_| ()).unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+    assert_eq!(
+        r.peek_map(9, |_| ()).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn stale_buffer_contents_are_never_exposed() {
+    let input: &[u8] = &[0u8; 300];
+    for &chunk in &[1, 7, 1000] {
+        let inner = Chunked { data: input, chunk };
+        let mut r = EnsuredBufReader::from_buffer_and_ensured_size(vec![0xffu8; 64], 16, inner);
+        r.set_auto_grow(true);
+
+        assert!(r
+            .fill_buf_to_expected_size(100)
+            .unwrap()
+            .iter()
+            .all(|&b| b == 0));
+        loop {
+            let n = {
+                let buf = r.fill_buf().unwrap();
+                assert!(buf.iter().all(|&b| b == 0), "chunk = {}", chunk);
+                buf.len()
+            };
+            if n == 0 {
+                break;
+            }
+            r.consume(n / 2 + 1);
+        }
+    }
+}
+
+struct Wrapped(Vec<u8>);
+
+impl AsRef<[u8]> for Wrapped {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsMut<[u8]> for Wrapped {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+#[test]
+fn map_buffer_keeps_state() {
+    let input: Vec<u8> = (0..=255).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, input.as_slice());
+    r.fill_buf().unwrap();
+    r.consume(10);
+
+    let mut r = r.map_buffer(Wrapped);
+    assert_eq!(r.position(), 10);
+    assert_eq!(r.get_capacity(), 64);
+    assert_eq!(r.buffer(), &input[10..64]);
+
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(&rest[..], &input[10..]);
+}
+
+#[test]
+#[should_panic]
+fn map_buffer_changing_length_panics() {
+    let input: &[u8] = b"abc";
+    let r = EnsuredBufReader::new(input);
+    let _ = r.map_buffer(|mut v| {
+        v.push(0);
+        v
+    });
+}
+
+#[test]
+fn read_line_bounded_skips_too_long_lines() {
+    let mut input = "a\n".to_string();
+    input.push_str(&"x".repeat(100));
+    input.push_str("\n\u{3042}\u{3044}\nend");
+    for &chunk in &[1, 5, 1000] {
+        let inner = Chunked {
+            data: input.as_bytes(),
+            chunk,
+        };
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 4, inner);
+        let mut line = String::new();
+
+        assert_eq!(
+            r.read_line_bounded(&mut line, 10).unwrap(),
+            LineOutcome::Complete(2)
+        );
+        assert_eq!(line, "a\n");
+
+        line.clear();
+        assert_eq!(
+            r.read_line_bounded(&mut line, 10).unwrap(),
+            LineOutcome::TooLong { read: 10 }
+        );
+        assert_eq!(line, "x".repeat(10));
+        assert!(r.process_line(|_| Ok(())).unwrap());
+
+        // Cut in the middle of the second character, which is left unconsumed.
+        line.clear();
+        assert_eq!(
+            r.read_line_bounded(&mut line, 4).unwrap(),
+            LineOutcome::TooLong { read: 3 }
+        );
+        assert_eq!(line, "\u{3042}");
+        assert!(r.process_line(|_| Ok(())).unwrap());
+
+        line.clear();
+        assert_eq!(
+            r.read_line_bounded(&mut line, 10).unwrap(),
+            LineOutcome::Eof(3)
+        );
+        assert_eq!(line, "end");
+        assert_eq!(
+            r.read_line_bounded(&mut line, 10).unwrap(),
+            LineOutcome::Eof(0)
+        );
+    }
+}
+
+#[test]
+fn read_line_bounded_keeps_cut_character_unconsumed() {
+    let input = "ab\u{3042}\u{1f600}cd\n".as_bytes();
+    for &chunk in &[1, 2, 3, 1000] {
+        for &(capacity, ensured) in &[(16, 4), (4, 1)] {
+            let inner = Chunked { data: input, chunk };
+            let mut r = EnsuredBufReader::with_capacity_and_ensured_size(capacity, ensured, inner);
+            let mut line = String::new();
+
+            // Cut inside U+3042.
+            assert_eq!(
+                r.read_line_bounded(&mut line, 4).unwrap(),
+                LineOutcome::TooLong { read: 2 }
+            );
+            assert_eq!(line, "ab");
+            assert_eq!(r.position(), 2);
+
+            // Cut inside U+1F600.
+            assert_eq!(
+                r.read_line_bounded(&mut line, 5).unwrap(),
+                LineOutcome::TooLong { read: 3 }
+            );
+            assert_eq!(line, "ab\u{3042}");
+            assert_eq!(r.position(), 5);
+
+            assert_eq!(
+                r.read_line_bounded(&mut line, 16).unwrap(),
+                LineOutcome::Complete(7)
+            );
+            assert_eq!(line, "ab\u{3042}\u{1f600}cd\n");
+        }
+    }
+
+    // The character doesn't fit in the buffer.
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(2, 1, input);
+    let mut line = String::new();
+    assert_eq!(
+        r.read_line_bounded(&mut line, 4).unwrap(),
+        LineOutcome::TooLong { read: 2 }
+    );
+    assert_eq!(line, "ab");
+}
+
+#[test]
+fn read_line_bounded_rejects_invalid_utf8() {
+    let input: &[u8] = b"a\xffb\n";
+    let mut r = EnsuredBufReader::new(input);
+    let mut line = String::new();
+    let err = r.read_line_bounded(&mut line, 10).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(line.is_empty());
+}
+
+#[test]
+fn counting_reader_counts_inner_reads() {
+    let input: &[u8] = &[0u8; 100];
+    let mut counter = CountingReader::new(Chunked {
+        data: input,
+        chunk: 10,
+    });
+    {
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 25, &mut counter);
+        assert_eq!(r.fill_buf().unwrap().len(), 30);
+    }
+    assert_eq!(counter.bytes_read(), 30);
+    assert_eq!(counter.read_calls(), 3);
+}
+
+#[test]
+fn read_until_u16_matches_aligned_units_only() {
+    let text = "\u{0a0a}a\nb\u{0a00}\nc";
+    let le: Vec<u8> = text
+        .encode_utf16()
+        .flat_map(|u| u.to_le_bytes().to_vec())
+        .collect();
+    let be: Vec<u8> = text
+        .encode_utf16()
+        .flat_map(|u| u.to_be_bytes().to_vec())
+        .collect();
+
+    for &chunk in &[1, 3, 100] {
+        let inner = Chunked { data: &le, chunk };
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(5, 1, inner);
+        let mut buf = Vec::new();
+        assert_eq!(r.read_until_u16_le(0x000a, &mut buf).unwrap(), 6);
+        assert_eq!(&buf[..], &le[..6]);
+        buf.clear();
+        assert_eq!(r.read_until_u16_le(0x000a, &mut buf).unwrap(), 6);
+        assert_eq!(&buf[..], &le[6..12]);
+        buf.clear();
+        assert_eq!(r.read_until_u16_le(0x000a, &mut buf).unwrap(), 2);
+        assert_eq!(&buf[..], &le[12..]);
+
+        let inner = Chunked { data: &be, chunk };
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(5, 1, inner);
+        let mut buf = Vec::new();
+        assert_eq!(r.read_until_u16_be(0x000a, &mut buf).unwrap(), 6);
+        assert_eq!(&buf[..], &be[..6]);
+    }
+}
+
+#[test]
+fn read_until_u16_keeps_alignment_from_start() {
+    let input: &[u8] = b"\x01\x0a\x00\x00\x0a\x00";
+    let mut r = EnsuredBufReader::new(input);
+    r.read_u8().unwrap();
+
+    // `0a 00` at offset 1 is not aligned.
+    let mut buf = Vec::new();
+    assert_eq!(r.read_until_u16_le(0x000a, &mut buf).unwrap(), 5);
+    assert_eq!(&buf[..], &input[1..]);
+}
+
+#[test]
+fn fill_buf_with_offset_tracks_position() {
+    let input: Vec<u8> = (0..=255).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 7,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 8, inner);
+
+    loop {
+        let n = {
+            let (buf, offset) = r.fill_buf_with_offset().unwrap();
+            if buf.is_empty() {
+                assert_eq!(offset, 256);
+                break;
+            }
+            assert_eq!(buf[0] as u64, offset);
+            buf.len() / 2 + 1
+        };
+        r.consume(n);
+    }
+}
+
+#[test]
+fn for_records_sizes() {
+    let input: &[u8] = &[0; 16];
+    let r = EnsuredBufReader::for_records(8, 3, input).unwrap();
+    assert_eq!(r.get_ensured_size(), 24);
+    assert_eq!(r.get_capacity(), 48);
+
+    assert_eq!(
+        EnsuredBufReader::for_records(0, 3, input).unwrap_err(),
+        ConfigError::ZeroEnsuredSize
+    );
+    assert_eq!(
+        EnsuredBufReader::for_records(8, 0, input).unwrap_err(),
+        ConfigError::ZeroEnsuredSize
+    );
+    assert_eq!(
+        EnsuredBufReader::for_records(std::usize::MAX / 2, 3, input).unwrap_err(),
+        ConfigError::SizeOverflow
+    );
+    assert_eq!(
+        EnsuredBufReader::for_records(std::usize::MAX / 2 + 1, 1, input).unwrap_err(),
+        ConfigError::SizeOverflow
+    );
+}
+
+struct Hinted {
+    remaining: usize,
+    preferred: Option<usize>,
+    reads: Vec<usize>,
+}
+
+impl Read for Hinted {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reads.push(buf.len());
+        let n = std::cmp::min(buf.len(), self.remaining);
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+impl ReadHint for Hinted {
+    fn preferred_read_size(&self) -> Option<usize> {
+        self.preferred
+    }
+}
+
+#[test]
+fn read_hint_rounds_read_size() {
+    let mut inner = Hinted {
+        remaining: 10_000,
+        preferred: Some(100),
+        reads: Vec::new(),
+    };
+    {
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1050, 1000, &mut inner);
+        r.set_use_read_hint(true);
+        r.set_max_read_chunk(250);
+        assert_eq!(r.fill_buf().unwrap().len(), 1000);
+        assert_eq!(r.fill_buf_to_expected_size(1050).unwrap().len(), 1050);
+    }
+    // Free space smaller than preferred size is read as is.
+    assert_eq!(inner.reads, vec![200, 200, 200, 200, 200, 50]);
+}
+
+#[test]
+fn read_hint_disabled_by_default() {
+    let mut inner = Hinted {
+        remaining: 10_000,
+        preferred: Some(100),
+        reads: Vec::new(),
+    };
+    {
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1050, 1000, &mut inner);
+        r.fill_buf().unwrap();
+    }
+    assert_eq!(inner.reads, vec![1050]);
+}
+
+#[test]
+fn into_remaining_reader_hands_off_stream() {
+    let input: Vec<u8> = (0..100).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 7,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 10, inner).into_drop_guard();
+    r.set_zero_on_drop(true);
+    r.fill_buf().unwrap();
+    r.consume(3);
+
+    let (mut out, mut rest) = r.into_inner().into_remaining_reader();
+    assert_eq!(out[0], 3);
+    rest.read_to_end(&mut out).unwrap();
+    assert_eq!(out, &input[3..]);
+}
+
+fn interrupted() -> std::io::Result<Vec<u8>> {
+    Err(std::io::Error::new(ErrorKind::Interrupted, "signal"))
+}
+
+// Same stream "abcdef", interrupted before first byte, mid-buffer and at buffer boundary.
+fn interrupted_script() -> Scripted {
+    Scripted {
+        results: vec![
+            interrupted(),
+            Ok(b"a".to_vec()),
+            interrupted(),
+            Ok(b"bc".to_vec()),
+            interrupted(),
+            interrupted(),
+            Ok(b"def".to_vec()),
+            interrupted(),
+        ],
+    }
+}
+
+#[test]
+fn interrupted_is_retried_by_fill_buf() {
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(3, 3, interrupted_script());
+    assert_eq!(r.fill_buf().unwrap(), b"abc");
+    r.consume(3);
+    assert_eq!(r.fill_buf().unwrap(), b"def");
+    r.consume(3);
+    assert_eq!(r.fill_buf().unwrap(), b"");
+}
+
+#[test]
+fn interrupted_is_retried_but_other_errors_propagate() {
+    let inner = Scripted {
+        results: vec![
+            interrupted(),
+            Ok(b"ab".to_vec()),
+            Err(std::io::Error::new(ErrorKind::Other, "broken")),
+            Ok(b"cd".to_vec()),
+        ],
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 1, inner);
+    assert_eq!(r.fill_buf().unwrap(), b"ab");
+
+    let err = r.fill_buf_to_expected_size(4).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(r.fill_buf_to_expected_size(4).unwrap(), b"abcd");
+}
+
+#[test]
+fn interrupted_is_retried_by_read_methods() {
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(3, 3, interrupted_script());
+    let mut one = [0u8; 1];
+    r.read_exact(&mut one).unwrap();
+    assert_eq!(&one, b"a");
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"bcde");
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"f");
+
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(3, 3, interrupted_script());
+    let mut out = [0u8; 6];
+    r.read_exact_bypassing(&mut out).unwrap();
+    assert_eq!(&out, b"abcdef");
+
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(3, 3, interrupted_script());
+    let mut line = String::new();
+    r.read_line(&mut line).unwrap();
+    assert_eq!(line, "abcdef");
+    assert_eq!(r.read(&mut buf).unwrap(), 0);
+}
+
+#[test]
+fn next_line_batch_limits() {
+    let input: &[u8] = b"one\ntwo\nthree\nfour\nfive";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 2, input);
+
+    assert_eq!(
+        r.next_line_batch(2, 1024).unwrap(),
+        vec![b"one\n".to_vec(), b"two\n".to_vec()]
+    );
+    // Byte limit is checked after each line.
+    assert_eq!(
+        r.next_line_batch(10, 7).unwrap(),
+        vec![b"three\n".to_vec(), b"four\n".to_vec()]
+    );
+    assert_eq!(r.next_line_batch(10, 1).unwrap(), vec![b"five".to_vec()]);
+    assert!(r.next_line_batch(10, 1024).unwrap().is_empty());
+}
+
+#[test]
+#[should_panic]
+fn next_line_batch_zero_lines_not_allowed() {
+    let input: &[u8] = b"a\n";
+    let _ = EnsuredBufReader::new(input).next_line_batch(0, 1024);
+}
+
+#[test]
+#[should_panic]
+fn next_line_batch_zero_bytes_not_allowed() {
+    let input: &[u8] = b"a\n";
+    let _ = EnsuredBufReader::new(input).next_line_batch(16, 0);
+}
+
+#[test]
+fn from_config_applies_settings() {
+    let input: &[u8] = &[0; 4096];
+    let config = ReaderConfig::default()
+        .capacity(64)
+        .ensured_size(16)
+        .auto_grow(true)
+        .max_capacity(Some(256))
+        .max_read_chunk(Some(8));
+    let mut counter = CountingReader::new(input);
+    {
+        let mut r = EnsuredBufReader::from_config(config, &mut counter).unwrap();
+        assert_eq!(r.get_capacity(), 64);
+        assert_eq!(r.get_ensured_size(), 16);
+        assert_eq!(r.fill_buf_to_expected_size(200).unwrap().len(), 200);
+        assert_eq!(r.get_capacity(), 200);
+        assert!(r.fill_buf_to_expected_size(257).is_err());
+    }
+    assert_eq!(counter.read_calls(), 25);
+}
+
+#[test]
+fn from_config_rejects_invalid_settings() {
+    let input: &[u8] = &[];
+    let mut config = ReaderConfig::default();
+    config.capacity = 8;
+    config.ensured_size = 16;
+    let err = EnsuredBufReader::from_config(config, input).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(
+        err.get_ref().unwrap().downcast_ref::<ConfigError>(),
+        Some(&ConfigError::CapacityTooSmall {
+            capacity: 8,
+            ensured_size: 16
+        })
+    );
+
+    let config = ReaderConfig::default().max_read_chunk(Some(0));
+    let err = EnsuredBufReader::from_config(config, input).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+fn assert_record_too_long(err: std::io::Error, limit: usize) {
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    let inner = err.get_ref().unwrap();
+    assert_eq!(
+        inner.downcast_ref::<RecordTooLongError>().unwrap().limit(),
+        limit
+    );
+}
+
+#[test]
+fn max_record_size_limits_read_until() {
+    let input: &[u8] = b"abcd,abcde,ab";
+    let inner = Chunked {
+        data: input,
+        chunk: 3,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(8, 2, inner);
+    r.set_max_record_size(Some(5));
+
+    // Exactly the limit, including delimiter.
+    let mut buf = Vec::new();
+    assert_eq!(r.read_until(b',', &mut buf).unwrap(), 5);
+    assert_eq!(buf, b"abcd,");
+
+    buf.clear();
+    assert_record_too_long(r.read_until(b',', &mut buf).unwrap_err(), 5);
+    assert_eq!(buf, b"abcde");
+
+    r.set_max_record_size(None);
+    buf.clear();
+    assert_eq!(r.read_until(b',', &mut buf).unwrap(), 1);
+    assert_eq!(r.read_until(b',', &mut buf).unwrap(), 2);
+    assert_eq!(buf, b",ab");
+}
+
+#[test]
+fn max_record_size_cuts_line_at_character_boundary() {
+    let input = "a\u{3042}b\nok\n".as_bytes();
+    let mut r = EnsuredBufReader::new(input);
+    r.set_max_record_size(Some(3));
+
+    let mut line = String::new();
+    assert_record_too_long(r.read_line(&mut line).unwrap_err(), 3);
+    assert_eq!(line, "a");
+    assert_eq!(r.position(), 3);
+}
+
+#[test]
+fn max_record_size_limits_line_methods() {
+    let input: &[u8] = b"ok\ntoo long\nok\n";
+    let mut r = EnsuredBufReader::new(input);
+    r.set_max_record_size(Some(4));
+
+    let mut line = String::new();
+    r.read_line(&mut line).unwrap();
+    assert_eq!(line, "ok\n");
+    assert_record_too_long(r.read_line(&mut line).unwrap_err(), 4);
+    assert_eq!(line, "ok\ntoo ");
+
+    // Per-call limit takes precedence.
+    line.clear();
+    assert_eq!(
+        r.read_line_bounded(&mut line, 16).unwrap(),
+        LineOutcome::Complete(5)
+    );
+    assert_eq!(line, "long\n");
+
+    let lines: Vec<String> = r.lines().map(|l| l.unwrap()).collect();
+    assert_eq!(lines, vec!["ok"]);
+}
+
+#[test]
+fn max_record_size_limits_read_until_slice_and_unit() {
+    let input: &[u8] = b"ab\r\nabcd\r\n";
+    let mut r = EnsuredBufReader::new(input);
+    r.set_max_record_size(Some(4));
+
+    let mut buf = Vec::new();
+    assert_eq!(r.read_until_slice(b"\r\n", &mut buf).unwrap(), 4);
+    buf.clear();
+    assert_record_too_long(r.read_until_slice(b"\r\n", &mut buf).unwrap_err(), 4);
+    assert_eq!(buf, b"abcd");
+
+    let input: &[u8] = b"a\0\n\0b\0c\0\n\0";
+    let mut r = EnsuredBufReader::new(input);
+    r.set_max_record_size(Some(4));
+
+    let mut buf = Vec::new();
+    assert_eq!(r.read_until_u16_le(0x000a, &mut buf).unwrap(), 4);
+    buf.clear();
+    assert_record_too_long(r.read_until_u16_le(0x000a, &mut buf).unwrap_err(), 4);
+    assert_eq!(buf, b"b\0c\0");
+}
+
+#[test]
+fn read_line_keeps_buf_on_invalid_utf8() {
+    let input: &[u8] = b"\xff\nok\n";
+    let mut r = EnsuredBufReader::new(input);
+
+    let mut line = String::from("x");
+    let err = r.read_line(&mut line).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(line, "x");
+
+    r.read_line(&mut line).unwrap();
+    assert_eq!(line, "xok\n");
+}
+
+#[test]
+fn fused_after_eof() {
+    let script = Scripted {
+        results: vec![Ok(b"ab".to_vec()), Ok(Vec::new()), Ok(b"cd".to_vec())],
+    };
+    let mut counter = CountingReader::new(script);
+    {
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(8, 4, &mut counter);
+        assert_eq!(r.fill_buf().unwrap(), b"ab");
+        assert!(r.is_fused());
+
+        r.consume(1);
+        assert_eq!(r.fill_buf().unwrap(), b"b");
+        let mut buf = [0u8; 4];
+        assert_eq!(r.read(&mut buf).unwrap(), 1);
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+        assert_eq!(r.fill_buf().unwrap(), b"");
+        assert_eq!(r.state().unwrap(), ReaderState::Eof);
+    }
+    assert_eq!(counter.read_calls(), 2);
+}
+
+#[test]
+#[should_panic(expected = "checkpoint")]
+fn transaction_rollback_restores_reached_checkpoints() {
+    let input: &[u8] = &[0u8; 100];
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 16, input);
+    r.push_checkpoint(4);
+
+    let result = r
+        .transaction(|t| {
+            t.read_exact(&mut [0u8; 4])?;
+            t.read_exact(&mut [0u8; 2])?;
+            Ok(Err::<(), _>(Rollback))
+        })
+        .unwrap();
+    assert_eq!(result, Err(Rollback));
+
+    // Replaying goes across the checkpoint.
+    r.read_exact(&mut [0u8; 6]).unwrap();
+}
+
+#[test]
+fn transaction_commits_and_rolls_back() {
+    let input: &[u8] = b"10 abc";
+    let mut r = EnsuredBufReader::new(input);
+
+    let number = r
+        .transaction(|t| {
+            let mut digits = Vec::new();
+            t.read_until(b' ', &mut digits)?;
+            assert_eq!(t.consumed(), 3);
+            Ok(Ok(digits))
+        })
+        .unwrap();
+    assert_eq!(number, Ok(b"10 ".to_vec()));
+    assert_eq!(r.position(), 3);
+
+    let rolled_back = r
+        .transaction(|t| {
+            let mut b = [0u8; 2];
+            t.read_exact(&mut b)?;
+            Ok(Err::<(), _>(Rollback))
+        })
+        .unwrap();
+    assert_eq!(rolled_back, Err(Rollback));
+    assert_eq!(r.position(), 3);
+    assert_eq!(r.fill_buf().unwrap(), b"abc");
+}
+
+#[test]
+fn transaction_fails_if_rollback_is_lost() {
+    let input: Vec<u8> = (0..100).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(8, 4, input.as_slice());
+
+    let err = r
+        .transaction(|t| {
+            let mut b = [0u8; 16];
+            t.read_exact(&mut b)?;
+            Ok(Err::<(), _>(Rollback))
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.get_ref().unwrap().is::<RollbackLostError>());
+    assert_eq!(r.position(), 16);
+}
+
+#[test]
+fn read_line_checked_reports_offset() {
+    let input = "héllo\nwörld\n".as_bytes().to_vec();
+    let mut bad = input.clone();
+    // Break second byte of "ö".
+    bad[9] = b'x';
+    // Cut last line in middle of a character.
+    bad.extend_from_slice(&"é".as_bytes()[..1]);
+    let inner = Chunked {
+        data: &bad,
+        chunk: 3,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 2, inner);
+    let mut line = String::new();
+
+    assert_eq!(r.read_line_checked(&mut line).unwrap(), Some(7));
+    match r.read_line_checked(&mut line).unwrap_err() {
+        Utf8LineError::InvalidUtf8 { offset, byte } => {
+            assert_eq!(offset, 8);
+            assert_eq!(byte, 0xc3);
+        }
+        e => panic!("unexpected error: {}", e),
+    }
+    match r.read_line_checked(&mut line).unwrap_err() {
+        Utf8LineError::InvalidUtf8 { offset, byte } => {
+            assert_eq!(offset, 14);
+            assert_eq!(byte, 0xc3);
+        }
+        e => panic!("unexpected error: {}", e),
+    }
+    assert_eq!(r.read_line_checked(&mut line).unwrap(), None);
+    assert_eq!(line, "héllo\n");
+
+    let err: std::io::Error = Utf8LineError::InvalidUtf8 { offset: 0, byte: 0 }.into();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn skip_byte_run_across_fills() {
+    let mut input = vec![b'0'; 100];
+    input.extend_from_slice(b"123");
+    let inner = Chunked {
+        data: &input,
+        chunk: 7,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 4, inner);
+
+    assert_eq!(r.skip_byte_run(b' ').unwrap(), 0);
+    assert_eq!(r.skip_byte_run(b'0').unwrap(), 100);
+    assert_eq!(r.position(), 100);
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"123");
+    assert_eq!(r.skip_byte_run(b'0').unwrap(), 0);
+}
+
+#[test]
+fn into_inner_drops_buffered_bytes() {
+    let input: Vec<u8> = (0..100).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 10, input.as_slice());
+    r.fill_buf().unwrap();
+    r.consume(3);
+
+    let rest = r.into_inner();
+    assert_eq!(rest, &input[16..]);
+}
+
+#[test]
+fn get_mut_reaches_inner_reader() {
+    let input: Vec<u8> = (0..100).collect();
+    let mut counter = CountingReader::new(input.as_slice());
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 10, &mut counter);
+    r.fill_buf().unwrap();
+    assert_eq!(r.get_ref().bytes_read(), 16);
+
+    // Reading out of band skips bytes from the stream.
+    let mut skipped = [0u8; 4];
+    r.get_mut().read_exact(&mut skipped).unwrap();
+    r.consume(16);
+    assert_eq!(r.fill_buf().unwrap()[0], 20);
+}
+
+#[test]
+fn reserve_capacity_keeps_buffered_bytes() {
+    let input: Vec<u8> = (0..=255).cycle().take(4096).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(256, 16, input.as_slice());
+    assert!(r.fill_buf_to_expected_size(1024).is_err());
+
+    r.fill_buf().unwrap();
+    r.consume(100);
+    r.reserve_capacity(1024);
+    assert_eq!(r.get_capacity(), 1024);
+    assert_eq!(r.buffer(), &input[100..256]);
+    assert_eq!(
+        r.fill_buf_to_expected_size(1024).unwrap(),
+        &input[100..1124]
+    );
+
+    // Never shrinks.
+    r.reserve_capacity(16);
+    assert_eq!(r.get_capacity(), 1024);
+}
+
+#[test]
+fn read_line_lossy_replaces_invalid_bytes() {
+    let input: &[u8] = b"a\xffb\n\xff";
+    let mut r = EnsuredBufReader::new(input);
+
+    assert_eq!(
+        r.read_line_lossy().unwrap(),
+        Some("a\u{fffd}b\n".to_string())
+    );
+    assert_eq!(r.read_line_lossy().unwrap(), Some("\u{fffd}".to_string()));
+    assert_eq!(r.read_line_lossy().unwrap(), None);
+}
+
+fn byte_lines_of(input: &[u8]) -> Vec<Vec<u8>> {
+    let inner = Chunked {
+        data: input,
+        chunk: 3,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 2, inner);
+    r.byte_lines().map(|l| l.unwrap()).collect()
+}
+
+#[test]
+fn byte_lines_yields_lines_with_terminator() {
+    assert_eq!(
+        byte_lines_of(b"first line\n\nthird\n"),
+        vec![
+            b"first line\n".to_vec(),
+            b"\n".to_vec(),
+            b"third\n".to_vec()
+        ]
+    );
+    assert_eq!(
+        byte_lines_of(b"first line\nlast"),
+        vec![b"first line\n".to_vec(), b"last".to_vec()]
+    );
+    assert!(byte_lines_of(b"").is_empty());
+}
+
+#[test]
+fn split_on_yields_empty_chunks() {
+    let input: &[u8] = b"a\0\0b\0";
+    let mut r = EnsuredBufReader::new(input);
+    let chunks: Vec<Vec<u8>> = r.split_on(0).map(|c| c.unwrap()).collect();
+    assert_eq!(chunks, vec![b"a".to_vec(), b"".to_vec(), b"b".to_vec()]);
+
+    let input: &[u8] = b"";
+    let mut r = EnsuredBufReader::new(input);
+    assert!(r.split_on(0).next().is_none());
+}
+
+#[test]
+fn read_vectored_fills_all_slices() {
+    use std::io::IoSliceMut;
+
+    let input: Vec<u8> = (0..10).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(10, 10, input.as_slice());
+
+    let (mut a, mut b, mut c) = ([0u8; 4], [0u8; 0], [0u8; 8]);
+    {
+        let mut bufs = [
+            IoSliceMut::new(&mut a),
+            IoSliceMut::new(&mut b),
+            IoSliceMut::new(&mut c),
+        ];
+        assert_eq!(r.read_vectored(&mut bufs).unwrap(), 10);
+    }
+    assert_eq!(a, [0, 1, 2, 3]);
+    assert_eq!(&c[..6], &[4, 5, 6, 7, 8, 9]);
+    assert_eq!(r.position(), 10);
+
+    // Doesn't read if all slices are empty.
+    let mut counter = CountingReader::new(input.as_slice());
+    {
+        let mut r = EnsuredBufReader::new(&mut counter);
+        let mut empty = [IoSliceMut::new(&mut b)];
+        assert_eq!(r.read_vectored(&mut empty).unwrap(), 0);
+        assert_eq!(r.read_vectored(&mut []).unwrap(), 0);
+    }
+    assert_eq!(counter.read_calls(), 0);
+}
+
+#[test]
+fn total_read_from_inner_counts_each_byte_once() {
+    let input: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 16, input.as_slice());
+
+    // Consuming unevenly forces compactions of the buffer.
+    let mut out = Vec::new();
+    while !r.fill_buf().unwrap().is_empty() {
+        let n = std::cmp::min(r.current_bytes(), 7);
+        out.extend_from_slice(&r.buffer()[..n]);
+        r.consume(n);
+        assert_eq!(
+            r.total_read_from_inner(),
+            r.position() + r.current_bytes() as u64
+        );
+    }
+    assert_eq!(out, input);
+    assert_eq!(r.total_read_from_inner(), 1000);
+
+    // Bypassing reads are counted too.
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 16, input.as_slice());
+    r.fill_buf().unwrap();
+    r.read_exact_bypassing(&mut [0u8; 500]).unwrap();
+    assert_eq!(r.total_read_from_inner(), 500);
+}
+
+#[test]
+fn total_consumed_counts_delivered_bytes() {
+    let input: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 16, input.as_slice());
+
+    let mut buf = [0u8; 30];
+    let mut delivered = 0;
+    while delivered < 500 {
+        delivered += r.read(&mut buf).unwrap();
+    }
+    assert_eq!(r.total_consumed(), delivered as u64);
+
+    r.fill_buf().unwrap();
+    r.consume(3);
+    assert_eq!(r.total_consumed(), delivered as u64 + 3);
+    assert!(r.total_consumed() < r.total_read_from_inner());
+}
+
+#[test]
+fn clear_discards_buffered_bytes() {
+    let input: Vec<u8> = (0..100).collect();
+    let mut counter = CountingReader::new(input.as_slice());
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 8, &mut counter);
+
+    r.fill_buf().unwrap();
+    r.consume(4);
+    r.clear();
+    assert_eq!(r.current_bytes(), 0);
+    assert!(r.buffer().is_empty());
+    assert_eq!(r.total_read_from_inner(), 16);
+
+    // Continues just after the discarded bytes.
+    assert_eq!(r.fill_buf().unwrap()[0], 16);
+    assert_eq!(r.get_capacity(), 16);
+}
+
+#[test]
+fn fill_buf_exact_errors_on_short_input() {
+    let input: &[u8] = b"abc";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(8, 1, input);
+
+    let err = r.fill_buf_exact(4).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(r.buffer(), b"abc");
+    assert_eq!(r.fill_buf_exact(3).unwrap(), b"abc");
+
+    let err = r.fill_buf_exact(9).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn take_ensured_limits_bytes() {
+    let input: Vec<u8> = (0..100).collect();
+    let mut r =
+        EnsuredBufReader::with_capacity_and_ensured_size(16, 8, input.as_slice()).take_ensured(5);
+
+    assert_eq!(r.fill_buf().unwrap(), &[0, 1, 2, 3, 4]);
+    let mut out = Vec::new();
+    r.read_to_end(&mut out).unwrap();
+    assert_eq!(out, [0, 1, 2, 3, 4]);
+    assert_eq!(r.limit(), 0);
+
+    // Bytes after the limit are kept.
+    let mut r = r.into_inner();
+    assert_eq!(r.position(), 5);
+    assert_eq!(r.fill_buf().unwrap()[0], 5);
+}
+
+#[test]
+fn take_ensured_read_returns_buffered_bytes_without_filling() {
+    let input: Vec<u8> = (0..100).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 4,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 8, inner).take_ensured(7);
+
+    let mut out = [0u8; 2];
+    assert_eq!(r.read(&mut out).unwrap(), 2);
+    assert_eq!(r.get_ref().total_read_from_inner(), 8);
+
+    // Buffered bytes are fewer than ensured size, but returned without reading, up to the limit.
+    r.read_exact(&mut [0u8; 4]).unwrap();
+    assert_eq!(r.read(&mut [0u8; 8]).unwrap(), 1);
+    assert_eq!(r.get_ref().total_read_from_inner(), 8);
+    assert_eq!(r.read(&mut out).unwrap(), 0);
+}
+
+#[test]
+#[should_panic(expected = "'ensure' must be positive.")]
+fn with_capacity_and_ensured_size_keeps_panic_message() {
+    let input: &[u8] = b"abc";
+    let _ = EnsuredBufReader::with_capacity_and_ensured_size(16, 0, input);
+}
+
+#[test]
+fn try_with_capacity_and_ensured_size_rejects_bad_sizes() {
+    let input: &[u8] = b"abc";
+    let err = EnsuredBufReader::try_with_capacity_and_ensured_size(16, 0, input).unwrap_err();
+    assert_eq!(err, ConfigError::ZeroEnsuredSize);
+    assert_eq!(err.to_string(), "'ensure' must be positive.");
+
+    let err = EnsuredBufReader::try_with_capacity_and_ensured_size(8, 16, input).unwrap_err();
+    assert_eq!(
+        err,
+        ConfigError::CapacityTooSmall {
+            capacity: 8,
+            ensured_size: 16
+        }
+    );
+
+    let mut r = EnsuredBufReader::try_with_capacity_and_ensured_size(16, 16, input).unwrap();
+    assert_eq!(r.get_capacity(), 16);
+    assert_eq!(r.fill_buf().unwrap(), b"abc");
+}
+
+#[test]
+fn expected_size_too_large_error_reports_sizes() {
+    let input: &[u8] = b"abc";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(512, 1, input);
+
+    let err = r.fill_buf_to_expected_size(513).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let inner = err.get_ref().unwrap();
+    let too_large = inner.downcast_ref::<ExpectedSizeTooLargeError>().unwrap();
+    assert_eq!(too_large.expected(), 513);
+    assert_eq!(too_large.capacity(), 512);
+    assert_eq!(too_large.max_capacity(), None);
+    assert_eq!(
+        inner.to_string(),
+        "requested 513 bytes but internal buffer capacity is 512."
+    );
+
+    // Reports the limit of growth.
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(16, 1, input);
+    r.set_auto_grow(true);
+    r.set_max_capacity(64);
+    let err = r.fill_buf_to_expected_size(65).unwrap_err();
+    let inner = err.get_ref().unwrap();
+    let too_large = inner.downcast_ref::<ExpectedSizeTooLargeError>().unwrap();
+    assert_eq!(too_large.capacity(), 16);
+    assert_eq!(too_large.max_capacity(), Some(64));
+    assert_eq!(
+        inner.to_string(),
+        "requested 65 bytes but internal buffer capacity is 16 and can grow up to 64."
+    );
+}
+
+#[test]
+fn peek_exact_does_not_consume() {
+    let input: &[u8] = b"HEADbody";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(8, 1, input);
+
+    let mut peeked = [0u8; 4];
+    r.peek_exact(&mut peeked).unwrap();
+    r.peek_exact(&mut peeked).unwrap();
+    assert_eq!(&peeked, b"HEAD");
+
+    let mut read = [0u8; 4];
+    r.read_exact(&mut read).unwrap();
+    assert_eq!(read, peeked);
+
+    let err = r.peek_exact(&mut [0u8; 5]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    let err = r.peek_exact(&mut [0u8; 9]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn is_eof_after_last_byte_is_consumed() {
+    let input: &[u8] = b"abcdef";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 4, input);
+    assert!(!r.is_eof());
+
+    r.fill_buf().unwrap();
+    r.consume(4);
+    assert!(!r.is_eof());
+
+    // EOF is observed while bytes remain buffered.
+    r.fill_buf().unwrap();
+    assert!(r.is_fused());
+    assert!(!r.is_eof());
+
+    r.consume(2);
+    assert!(r.is_eof());
+    assert!(r.fill_buf().unwrap().is_empty());
+}
+
+#[test]
+fn fill_buf_growing_keeps_buffered_bytes() {
+    let input: Vec<u8> = (0..=255).cycle().take(8192).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(512, 16, input.as_slice());
+    r.fill_buf().unwrap();
+    r.consume(100);
+
+    let buf = r.fill_buf_growing(4096).unwrap();
+    assert_eq!(buf.len(), 4096);
+    assert_eq!(buf, &input[100..4196]);
+    assert_eq!(r.get_capacity(), 4096);
+
+    // Doesn't shrink for smaller sizes.
+    assert!(r.fill_buf_growing(16).unwrap().len() >= 16);
+    assert_eq!(r.get_capacity(), 4096);
+}
+
+#[test]
+fn clone_continues_independently() {
+    let input: &[u8] = b"abcdefgh";
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 2, input);
+    let mut buf = [0u8; 3];
+    r.read_exact(&mut buf).unwrap();
+
+    let mut cloned = r.clone();
+    let mut rest = String::new();
+    r.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "defgh");
+    assert_eq!(r.position(), 8);
+
+    assert_eq!(cloned.position(), 3);
+    assert_eq!(cloned.buffer(), b"d");
+    let mut cloned_rest = String::new();
+    cloned.read_to_string(&mut cloned_rest).unwrap();
+    assert_eq!(cloned_rest, "defgh");
+}
+
+#[test]
+fn into_parts_and_from_parts_round_trip() {
+    let input: Vec<u8> = (0..100).collect();
+    let mut r =
+        EnsuredBufReader::with_capacity_and_ensured_size(16, 8, input.as_slice()).into_drop_guard();
+    r.set_zero_on_drop(true);
+    r.fill_buf().unwrap();
+    r.consume(5);
+
+    let (inner, buf, pos, cap, ensured_size) = r.into_inner().into_parts();
+    assert_eq!((pos, cap, ensured_size), (5, 16, 8));
+    assert_eq!(&buf[pos..cap], &input[5..16]);
+
+    let mut r = EnsuredBufReader::from_parts(inner, buf, pos, cap, ensured_size).unwrap();
+    assert_eq!(r.buffer(), &input[5..16]);
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, &input[5..]);
+}
+
+#[test]
+fn from_parts_validates_parts() {
+    let input: &[u8] = b"";
+    assert_eq!(
+        EnsuredBufReader::from_parts(input, vec![0; 16], 4, 3, 8).unwrap_err(),
+        ConfigError::InvalidBufferedRange {
+            pos: 4,
+            cap: 3,
+            capacity: 16
+        }
+    );
+    assert_eq!(
+        EnsuredBufReader::from_parts(input, vec![0; 16], 0, 17, 8).unwrap_err(),
+        ConfigError::InvalidBufferedRange {
+            pos: 0,
+            cap: 17,
+            capacity: 16
+        }
+    );
+    assert_eq!(
+        EnsuredBufReader::from_parts(input, vec![0; 16], 0, 0, 0).unwrap_err(),
+        ConfigError::ZeroEnsuredSize
+    );
+    assert_eq!(
+        EnsuredBufReader::from_parts(input, vec![0; 16], 0, 0, 17).unwrap_err(),
+        ConfigError::CapacityTooSmall {
+            capacity: 16,
+            ensured_size: 17
+        }
+    );
+}
+
+#[test]
+fn skip_across_buffer_boundary() {
+    let input: Vec<u8> = (0..=255).cycle().take(20000).collect();
+    let mut r = EnsuredBufReader::new(input.as_slice());
+    assert_eq!(r.get_capacity(), 8 * 1024);
+    r.fill_buf().unwrap();
+    r.consume(1);
+
+    assert_eq!(r.skip(10000).unwrap(), 10000);
+    assert_eq!(r.position(), 10001);
+    let mut next = [0u8; 1];
+    r.read_exact(&mut next).unwrap();
+    assert_eq!(next[0], input[10001]);
+
+    // Stops at EOF.
+    assert_eq!(r.skip(20000).unwrap(), 20000 - 10002);
+    assert_eq!(r.skip(1).unwrap(), 0);
+}
+
+#[test]
+fn read_to_end_matches_fs_read() {
+    let expected = std::fs::read("src/lib.rs").unwrap();
+    let f = std::fs::File::open("src/lib.rs").unwrap();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(256, 16, f);
+    r.fill_buf().unwrap();
+    r.consume(10);
+
+    // Appends to existing data.
+    let mut out = b"existing".to_vec();
+    let n = r.read_to_end(&mut out).unwrap();
+    assert_eq!(n, expected.len() - 10);
+    assert_eq!(&out[..8], b"existing");
+    assert_eq!(&out[8..], &expected[10..]);
+    assert_eq!(r.read_to_end(&mut out).unwrap(), 0);
+}
+
+#[test]
+fn fill_to_capacity_fills_whole_buffer() {
+    let input: Vec<u8> = (0..=255).cycle().take(10000).collect();
+    let inner = Chunked {
+        data: &input,
+        chunk: 100,
+    };
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(1024, 16, inner);
+    r.fill_buf().unwrap();
+    r.consume(10);
+
+    assert_eq!(r.fill_to_capacity().unwrap(), &input[10..1034]);
+    assert_eq!(r.current_bytes(), r.get_capacity());
+
+    // Fewer bytes at EOF.
+    r.skip(9000).unwrap();
+    assert_eq!(r.fill_to_capacity().unwrap().len(), 10000 - 9010);
+}
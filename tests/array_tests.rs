@@ -0,0 +1,24 @@
+#![cfg(feature = "const_generics")]
+
+use std::io::Read;
+
+use ensured_bufreader::{EnsuredBufReader, DEFAULT_ENSURED_BYTES};
+
+#[test]
+fn with_array_reads_through_array() {
+    let input: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let mut r = EnsuredBufReader::<_, [u8; 256]>::with_array(input.as_slice());
+    assert_eq!(r.get_capacity(), 256);
+    assert_eq!(r.get_ensured_size(), DEFAULT_ENSURED_BYTES);
+
+    let mut out = Vec::new();
+    r.read_to_end(&mut out).unwrap();
+    assert_eq!(out, input);
+}
+
+#[test]
+#[should_panic]
+fn with_array_smaller_than_default_ensure_not_allowed() {
+    let input: &[u8] = &[];
+    let _ = EnsuredBufReader::<_, [u8; 64]>::with_array(input);
+}
@@ -0,0 +1,123 @@
+use std::io::{BufRead, ErrorKind};
+
+use ensured_bufreader::{Endian, EnsuredBufReader};
+
+#[test]
+fn read_integers_with_explicit_endianness() {
+    let input: &[u8] = &[
+        0x01, 0x02, 0x01, 0x02, 0xff, 0xff, 0xff, 0xfe, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+        0x08, 0x80,
+    ];
+    let mut r = EnsuredBufReader::new(input);
+
+    assert_eq!(r.read_u16_be().unwrap(), 0x0102);
+    assert_eq!(r.read_u16_le().unwrap(), 0x0201);
+    assert_eq!(r.read_i32_be().unwrap(), -2);
+    assert_eq!(r.read_u64_le().unwrap(), 0x0807_0605_0403_0201);
+    assert_eq!(r.read_i8().unwrap(), -128);
+
+    let err = r.read_u8().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn read_integers_with_default_endianness() {
+    let input: &[u8] = &[0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00];
+    let mut r = EnsuredBufReader::new(input);
+    assert_eq!(r.endianness(), Endian::Big);
+    assert_eq!(r.read_u32().unwrap(), 1);
+
+    r.set_endianness(Endian::Little);
+    assert_eq!(r.read_u32().unwrap(), 1);
+}
+
+#[test]
+fn read_integers_larger_than_capacity() {
+    let input: Vec<u8> = (1..=16).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(4, 2, &input[..]);
+
+    assert_eq!(r.read_u64_be().unwrap(), 0x0102_0304_0506_0708);
+    assert_eq!(r.read_u64_le().unwrap(), 0x100f_0e0d_0c0b_0a09);
+    assert_eq!(r.get_capacity(), 4);
+
+    let err = r.read_u64_be().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn read_integer_at_eof_keeps_buffered_bytes() {
+    let input: &[u8] = &[0x01, 0x02, 0x03];
+    let mut r = EnsuredBufReader::new(input);
+
+    let err = r.read_u32_be().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(r.fill_buf().unwrap(), &[0x01, 0x02, 0x03]);
+}
+
+#[test]
+fn read_floats_preserves_nan_bits() {
+    let nan32: u32 = 0x7fc0_1234;
+    let nan64: u64 = 0x7ff8_0000_dead_beef;
+    let mut input = Vec::new();
+    input.extend_from_slice(&1.5f32.to_bits().to_be_bytes());
+    input.extend_from_slice(&nan32.to_le_bytes());
+    input.extend_from_slice(&(-2.25f64).to_bits().to_le_bytes());
+    input.extend_from_slice(&nan64.to_be_bytes());
+    let mut r = EnsuredBufReader::new(input.as_slice());
+
+    assert_eq!(r.read_f32_be().unwrap(), 1.5);
+    assert_eq!(r.read_f32_le().unwrap().to_bits(), nan32);
+    assert_eq!(r.read_f64_le().unwrap(), -2.25);
+    assert_eq!(r.read_f64().unwrap().to_bits(), nan64);
+
+    let err = r.read_f32_be().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn read_bool_rejects_invalid_byte() {
+    let input: &[u8] = &[0, 1, 2];
+    let mut r = EnsuredBufReader::new(input);
+
+    assert!(!r.read_bool().unwrap());
+    assert!(r.read_bool().unwrap());
+    let err = r.read_bool().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(r.fill_buf().unwrap(), &[2]);
+
+    r.consume(1);
+    let err = r.read_bool().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn read_length_prefixed_strings() {
+    let mut input = Vec::new();
+    input.extend_from_slice(b"\x00\x03abc");
+    input.extend_from_slice(b"\x00\x00\x00\x02\xff\xfe");
+    input.extend_from_slice(b"\x00\x00\x00\x02\xff\xfe");
+    input.extend_from_slice(b"\x00\x00\x01\x00");
+    let mut r = EnsuredBufReader::new(input.as_slice());
+
+    assert_eq!(r.read_string_u16_be(3).unwrap(), "abc");
+
+    let err = r.read_string_u32_be(16).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(r.read_string_u32_be_lossy(16).unwrap(), "\u{fffd}\u{fffd}");
+
+    let err = r.read_string_u32_be(255).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn read_string_longer_than_capacity() {
+    let s = "x".repeat(1000);
+    let mut input = vec![0x03, 0xe8];
+    input.extend_from_slice(s.as_bytes());
+    input.extend_from_slice(b"\x00\x05");
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, input.as_slice());
+
+    assert_eq!(r.read_string_u16_be_lossy(1000).unwrap(), s);
+    let err = r.read_string_u16_be(1000).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,49 @@
+use std::io::{BufRead, BufReader, Read};
+
+use ensured_bufreader::EnsuredGuard;
+
+#[test]
+fn guard_passes_through_large_inner_buffer() {
+    let input: Vec<u8> = (0..100).collect();
+    let mut inner = BufReader::with_capacity(64, input.as_slice());
+    let inner_ptr = inner.fill_buf().unwrap().as_ptr();
+    let mut r = EnsuredGuard::with_ensured_size(16, inner);
+
+    let buf = r.fill_buf().unwrap();
+    assert_eq!(buf.as_ptr(), inner_ptr);
+    assert_eq!(buf.len(), 64);
+}
+
+#[test]
+fn guard_ensures_bytes_over_small_inner_buffer() {
+    let input: Vec<u8> = (0..100).collect();
+    let inner = BufReader::with_capacity(3, input.as_slice());
+    let mut r = EnsuredGuard::with_ensured_size(8, inner);
+
+    let mut out = Vec::new();
+    loop {
+        let n = {
+            let buf = r.fill_buf().unwrap();
+            if buf.is_empty() {
+                break;
+            }
+            let rest = input.len() - out.len();
+            assert!(buf.len() >= 8 || buf.len() == rest);
+            out.extend_from_slice(&buf[..1]);
+            1
+        };
+        r.consume(n);
+    }
+    assert_eq!(out, input);
+}
+
+#[test]
+fn guard_reads_whole_stream() {
+    let input: Vec<u8> = (0..=255).collect();
+    let inner = BufReader::with_capacity(5, input.as_slice());
+    let mut r = EnsuredGuard::with_ensured_size(7, inner);
+
+    let mut out = Vec::new();
+    r.read_to_end(&mut out).unwrap();
+    assert_eq!(out, input);
+}
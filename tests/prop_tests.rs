@@ -0,0 +1,91 @@
+//! Randomized tests of buffer invariants over chunked readers.
+
+use std::cmp;
+use std::io::{BufRead, ErrorKind, Read};
+
+use ensured_bufreader::EnsuredBufReader;
+
+// xorshift64*, to keep tests deterministic without dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // Returns a value in `lo..=hi`.
+    fn range(&mut self, lo: usize, hi: usize) -> usize {
+        lo + (self.next() % (hi - lo + 1) as u64) as usize
+    }
+}
+
+// Returns random sized chunks on each read.
+struct RandomChunks {
+    data: Vec<u8>,
+    pos: usize,
+    rng: Rng,
+    max_chunk: usize,
+}
+
+impl Read for RandomChunks {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let chunk = self.rng.range(1, self.max_chunk);
+        let n = cmp::min(cmp::min(chunk, buf.len()), self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn fill_invariants_hold_for_random_operations() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+
+    for _ in 0..500 {
+        let len = rng.range(0, 2000);
+        let data: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+        let ensured_size = rng.range(1, 64);
+        let capacity = rng.range(ensured_size, 128);
+        let inner = RandomChunks {
+            data: data.clone(),
+            pos: 0,
+            rng: Rng(rng.next() | 1),
+            max_chunk: rng.range(1, 200),
+        };
+        let mut r = EnsuredBufReader::with_capacity_and_ensured_size(capacity, ensured_size, inner);
+
+        let mut consumed = 0;
+        loop {
+            let remaining = len - consumed;
+            let (buf, expected) = if rng.range(0, 1) == 0 {
+                (r.fill_buf().unwrap(), ensured_size)
+            } else {
+                let n = rng.range(0, capacity + 8);
+                match r.fill_buf_to_expected_size(n) {
+                    Ok(buf) => (buf, n),
+                    Err(e) => {
+                        assert!(n > capacity);
+                        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+                        continue;
+                    }
+                }
+            };
+
+            assert!(buf.len() <= capacity);
+            assert!(buf.len() >= cmp::min(expected, remaining));
+            assert_eq!(buf, &data[consumed..consumed + buf.len()]);
+            if remaining == 0 {
+                assert!(buf.is_empty());
+                break;
+            }
+
+            let amt = rng.range(0, buf.len());
+            r.consume(amt);
+            consumed += amt;
+            assert!(r.buffer().len() <= r.get_capacity());
+        }
+    }
+}
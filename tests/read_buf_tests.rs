@@ -0,0 +1,27 @@
+#![cfg(feature = "read_buf")]
+#![feature(read_buf, core_io_borrowed_buf)]
+
+use std::io::{BorrowedBuf, Read};
+use std::mem::MaybeUninit;
+
+use ensured_bufreader::EnsuredBufReader;
+
+#[test]
+fn read_buf_fills_uninit_buffer() {
+    let input: Vec<u8> = (0..100).collect();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(32, 8, input.as_slice());
+
+    let mut storage = [MaybeUninit::<u8>::uninit(); 40];
+    let mut buf: BorrowedBuf<'_> = (&mut storage[..]).into();
+
+    // Only buffered bytes are copied by a call.
+    r.read_buf(buf.unfilled()).unwrap();
+    assert_eq!(buf.filled(), &input[..32]);
+    r.read_buf(buf.unfilled()).unwrap();
+    assert_eq!(buf.filled(), &input[..40]);
+    assert_eq!(r.position(), 40);
+
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, &input[40..]);
+}
@@ -0,0 +1,206 @@
+use std::io::{BufRead, Cursor, ErrorKind, Read, Seek, SeekFrom};
+
+use std::sync::{Arc, Mutex};
+
+use ensured_bufreader::{EnsuredBufReader, FinalStats};
+
+fn input() -> Vec<u8> {
+    (0..=255).cycle().take(1000).collect()
+}
+
+#[test]
+fn reset_to_mark_within_buffer() {
+    let input = input();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, Cursor::new(&input));
+
+    r.fill_buf().unwrap();
+    r.consume(10);
+    r.mark();
+    r.consume(20);
+    assert_eq!(r.position(), 30);
+
+    r.reset_to_mark().unwrap();
+    assert_eq!(r.position(), 10);
+    assert_eq!(r.fill_buf().unwrap()[0], input[10]);
+}
+
+#[test]
+fn reset_to_mark_seeks_if_buffer_is_shifted() {
+    let input = input();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, Cursor::new(&input));
+
+    let mut buf = [0u8; 10];
+    r.read_exact(&mut buf).unwrap();
+    r.mark();
+
+    let mut buf = [0u8; 500];
+    r.read_exact(&mut buf).unwrap();
+    r.reset_to_mark().unwrap();
+    assert_eq!(r.position(), 10);
+
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(&rest[..], &input[10..]);
+}
+
+#[test]
+#[should_panic(expected = "checkpoint")]
+fn reset_to_mark_restores_reached_checkpoints() {
+    let input = input();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, Cursor::new(&input));
+    r.mark();
+    r.push_checkpoint(4);
+
+    r.read_exact(&mut [0u8; 4]).unwrap();
+    r.read_exact(&mut [0u8; 2]).unwrap();
+    r.reset_to_mark().unwrap();
+
+    // Replaying goes across the checkpoint.
+    r.read_exact(&mut [0u8; 6]).unwrap();
+}
+
+#[test]
+fn reset_to_mark_keeps_checkpoints_after_seeking() {
+    let input = input();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, Cursor::new(&input));
+    r.push_checkpoint(10);
+    r.mark();
+    r.push_checkpoint(600);
+
+    r.read_exact(&mut [0u8; 10]).unwrap();
+    r.read_exact(&mut [0u8; 500]).unwrap();
+    r.reset_to_mark().unwrap();
+
+    r.read_exact(&mut [0u8; 10]).unwrap();
+    r.read_exact(&mut [0u8; 590]).unwrap();
+    assert_eq!(r.position(), 600);
+}
+
+#[test]
+fn reset_to_mark_without_mark_fails() {
+    let mut r = EnsuredBufReader::new(Cursor::new(input()));
+    let err = r.reset_to_mark().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn inner_position_is_ahead_by_buffered_bytes() {
+    let input = input();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, Cursor::new(&input));
+    assert_eq!(r.inner_position().unwrap(), 0);
+
+    let mut buf = [0u8; 100];
+    r.read_exact(&mut buf).unwrap();
+    r.fill_buf().unwrap();
+    assert_eq!(
+        r.inner_position().unwrap(),
+        r.position() + r.current_bytes() as u64
+    );
+}
+
+#[test]
+fn read_at_keeps_position_and_buffer() {
+    let input = input();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, Cursor::new(&input));
+
+    r.fill_buf().unwrap();
+    r.consume(10);
+    let buffered = r.buffer().to_vec();
+    let inner_position = r.inner_position().unwrap();
+
+    let mut out = [0u8; 100];
+    assert_eq!(r.read_at(500, &mut out).unwrap(), 100);
+    assert_eq!(&out[..], &input[500..600]);
+    assert_eq!(r.read_at(990, &mut out).unwrap(), 10);
+    assert_eq!(&out[..10], &input[990..]);
+
+    assert_eq!(r.position(), 10);
+    assert_eq!(r.inner_position().unwrap(), inner_position);
+    assert_eq!(r.buffer(), buffered.as_slice());
+
+    let mut rest = Vec::new();
+    r.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, &input[10..]);
+}
+
+#[test]
+fn seek_discards_buffer() {
+    let input = input();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, Cursor::new(&input));
+
+    r.fill_buf().unwrap();
+    r.consume(10);
+    assert_eq!(r.seek(SeekFrom::Current(0)).unwrap(), 10);
+    assert_eq!(r.fill_buf().unwrap(), &input[10..74]);
+
+    // Forward, relative to logical position.
+    r.consume(5);
+    assert_eq!(r.seek(SeekFrom::Current(100)).unwrap(), 115);
+    assert_eq!(r.position(), 115);
+    assert_eq!(r.fill_buf().unwrap(), &input[115..179]);
+
+    // Backward.
+    r.consume(1);
+    assert_eq!(r.seek(SeekFrom::Current(-50)).unwrap(), 66);
+    assert_eq!(r.fill_buf().unwrap(), &input[66..130]);
+
+    assert_eq!(r.seek(SeekFrom::Start(3)).unwrap(), 3);
+    assert_eq!(r.fill_buf().unwrap(), &input[3..67]);
+
+    assert_eq!(r.seek(SeekFrom::End(-2)).unwrap(), 998);
+    assert_eq!(r.fill_buf().unwrap(), &input[998..]);
+}
+
+#[test]
+fn seek_clears_eof_and_mark() {
+    let input = input();
+    let mut r = EnsuredBufReader::new(Cursor::new(&input));
+
+    r.mark();
+    r.read_to_end(&mut Vec::new()).unwrap();
+    assert!(r.is_fused());
+
+    r.seek(SeekFrom::Start(0)).unwrap();
+    assert!(!r.is_fused());
+    assert!(r.reset_to_mark().is_err());
+    let mut all = Vec::new();
+    r.read_to_end(&mut all).unwrap();
+    assert_eq!(all, input);
+}
+
+#[test]
+fn stream_position_keeps_buffer() {
+    let input = input();
+    let mut r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, Cursor::new(&input));
+
+    r.fill_buf().unwrap();
+    r.consume(10);
+    assert_eq!(r.stream_position().unwrap(), 10);
+    assert_eq!(r.current_bytes(), 54);
+    assert_eq!(r.fill_buf().unwrap(), &input[10..64]);
+
+    // Underlying reader seeked directly behind buffered bytes.
+    r.get_mut().set_position(0);
+    let err = r.stream_position().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn final_stats_separates_position_and_consumed() {
+    let input = input();
+    let stats: Arc<Mutex<Option<FinalStats>>> = Arc::new(Mutex::new(None));
+
+    {
+        let r = EnsuredBufReader::with_capacity_and_ensured_size(64, 8, Cursor::new(&input));
+        let mut r = r.into_drop_guard();
+        let s = stats.clone();
+        r.set_on_drop(Box::new(move |st| *s.lock().unwrap() = Some(*st)));
+        r.read_exact(&mut [0u8; 10]).unwrap();
+        r.get_mut().seek(SeekFrom::Start(500)).unwrap();
+        r.read_exact(&mut [0u8; 5]).unwrap();
+    }
+
+    let stats = stats.lock().unwrap().unwrap();
+    assert_eq!(stats.position(), 505);
+    assert_eq!(stats.consumed(), 15);
+}
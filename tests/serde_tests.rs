@@ -0,0 +1,31 @@
+#![cfg(feature = "serde")]
+
+use serde::de::value::{Error, MapDeserializer};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use ensured_bufreader::{Endian, ReaderConfig};
+
+fn assert_serde<T: Serialize + DeserializeOwned>() {}
+
+#[test]
+fn reader_config_implements_serde() {
+    assert_serde::<ReaderConfig>();
+    assert_serde::<Endian>();
+}
+
+#[test]
+fn reader_config_missing_fields_are_default() {
+    let fields = vec![("capacity", 64usize), ("ensured_size", 16)];
+    let config =
+        ReaderConfig::deserialize(MapDeserializer::<_, Error>::new(fields.into_iter())).unwrap();
+    assert_eq!(
+        config,
+        ReaderConfig::default().capacity(64).ensured_size(16)
+    );
+
+    let fields: Vec<(&str, usize)> = Vec::new();
+    let config =
+        ReaderConfig::deserialize(MapDeserializer::<_, Error>::new(fields.into_iter())).unwrap();
+    assert_eq!(config, ReaderConfig::default());
+}
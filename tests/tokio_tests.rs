@@ -0,0 +1,122 @@
+#![cfg(feature = "tokio")]
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::ptr;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use ensured_bufreader::AsyncEnsuredBufReader;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, ReadBuf};
+
+// Runtime is not needed because `Trickle` never waits for real I/O.
+fn block_on<F: Future>(f: F) -> F::Output {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut f = Box::pin(f);
+    loop {
+        if let Poll::Ready(v) = f.as_mut().poll(&mut cx) {
+            return v;
+        }
+    }
+}
+
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    unsafe { Waker::from_raw(clone(ptr::null())) }
+}
+
+// Returns `Poll::Pending` before each chunk.
+struct Trickle {
+    chunks: Vec<Vec<u8>>,
+    ready: bool,
+}
+
+impl Trickle {
+    fn new(data: &[u8], chunk: usize) -> Trickle {
+        Trickle {
+            chunks: data.chunks(chunk).map(|c| c.to_vec()).collect(),
+            ready: false,
+        }
+    }
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.ready = false;
+        if !self.chunks.is_empty() {
+            let n = std::cmp::min(buf.remaining(), self.chunks[0].len());
+            buf.put_slice(&self.chunks[0][..n]);
+            self.chunks[0].drain(..n);
+            if self.chunks[0].is_empty() {
+                self.chunks.remove(0);
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn fill_buf_ensures_bytes_across_pending() {
+    let input: Vec<u8> = (0u8..24).collect();
+    let mut r =
+        AsyncEnsuredBufReader::with_capacity_and_ensured_size(16, 8, Trickle::new(&input, 3));
+
+    let read = block_on(async {
+        let mut read = Vec::new();
+        loop {
+            let buf = r.fill_buf().await.unwrap();
+            if buf.is_empty() {
+                return read;
+            }
+            assert!(buf.len() >= std::cmp::min(8, 24 - read.len()));
+            read.push(buf[0]);
+            r.consume(1);
+        }
+    });
+    assert_eq!(read, input);
+}
+
+#[test]
+fn poll_fill_buf_keeps_bytes_read_before_pending() {
+    let input: &[u8] = b"abcdef";
+    let mut r = AsyncEnsuredBufReader::with_capacity_and_ensured_size(8, 4, Trickle::new(input, 2));
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    assert!(Pin::new(&mut r).poll_fill_buf(&mut cx).is_pending());
+    assert!(Pin::new(&mut r).poll_fill_buf(&mut cx).is_pending());
+    assert_eq!(r.buffer(), b"ab");
+    match Pin::new(&mut r).poll_fill_buf(&mut cx) {
+        Poll::Ready(Ok(buf)) => assert_eq!(buf, b"abcd"),
+        _ => panic!("expected ready"),
+    }
+}
+
+#[test]
+fn read_returns_buffered_bytes() {
+    let input: &[u8] = b"abcdef";
+    let mut r = AsyncEnsuredBufReader::with_capacity_and_ensured_size(16, 4, input);
+
+    block_on(async {
+        let mut out = [0u8; 2];
+        assert_eq!(r.read(&mut out).await.unwrap(), 2);
+        assert_eq!(&out, b"ab");
+        let mut rest = Vec::new();
+        r.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"cdef");
+    });
+}